@@ -1,16 +1,30 @@
+use closest_pairs::cli::resolve_threads;
+use closest_pairs::closest::approximate::Approximate;
+use closest_pairs::closest::incremental::RandomizedIncremental;
+use closest_pairs::closest::sweep::PlaneSweep;
 use closest_pairs::closest::task_1::Task1;
 use closest_pairs::closest::task_3_1::Task3QuickSort;
 use closest_pairs::closest::task_3_2::Task3SortedY;
-use closest_pairs::closest::task_4::Task4;
-use closest_pairs::geometry::Point;
-use closest_pairs::quick_select::quick_select_points;
+use closest_pairs::closest::task_4::{Task4, TASK_4_SEED};
+use closest_pairs::closest::{Algorithm, Options};
+use closest_pairs::dynamic::DynamicClosestPair;
+use closest_pairs::generators;
+use closest_pairs::kdtree::KdTree;
+use closest_pairs::quick_select::{
+    quick_select_points, quick_select_points_median_of_three, Select,
+};
 use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
-use itertools::Itertools;
 use rand::prelude::*;
 use std::time::Duration;
 
 const FACTOR: f32 = 1.5;
 
+/// The QuickSelect variants compared in the `quick_select_*` groups.
+const SELECTS: [(&str, Select); 2] = [
+    ("introselect", quick_select_points),
+    ("median_of_three", quick_select_points_median_of_three),
+];
+
 pub fn closest_pair_average(c: &mut Criterion) {
     const ITER_MIN: usize = 10;
     const ITER_MAX: usize = 30;
@@ -18,14 +32,8 @@ pub fn closest_pair_average(c: &mut Criterion) {
     let mut group = c.benchmark_group("closest_pair_average");
     let mut rng = StdRng::seed_from_u64(0xDEAD_BEEF);
 
-    let trials = (ITER_MIN..=ITER_MAX).map(|x| {
-        (0_usize..(FACTOR.powf(x as f32) as usize))
-            .map(|_| Point {
-                x: rng.gen::<i64>() as f64,
-                y: rng.gen::<i64>() as f64,
-            })
-            .collect_vec()
-    });
+    let trials = (ITER_MIN..=ITER_MAX)
+        .map(|x| generators::uniform(&mut rng, FACTOR.powf(x as f32) as usize));
 
     for trial in trials {
         // Task 1
@@ -79,7 +87,86 @@ pub fn closest_pair_average(c: &mut Criterion) {
             &trial,
             move |b, t| {
                 b.iter_batched(
-                    || Task4::new(t.clone()),
+                    || Task4::new(t.clone()).with_seed(TASK_4_SEED),
+                    |data| {
+                        data.find_closest_pair();
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
+        // Task 4: Simple-Randomised, parallel sieve on every CPU
+        group.bench_with_input(
+            BenchmarkId::new("task_4_parallel", trial.len()),
+            &trial,
+            move |b, t| {
+                b.iter_batched(
+                    || {
+                        Task4::new(t.clone())
+                            .with_seed(TASK_4_SEED)
+                            .with_threads(resolve_threads(0))
+                    },
+                    |data| {
+                        data.find_closest_pair();
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
+        // k-d tree: nearest neighbour of every point
+        group.bench_with_input(
+            BenchmarkId::new("kd_tree", trial.len()),
+            &trial,
+            move |b, t| {
+                b.iter_batched(
+                    || t.clone(),
+                    |data| {
+                        KdTree::new(data).closest_pair();
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
+        // Randomised incremental grid
+        group.bench_with_input(
+            BenchmarkId::new("randomized_incremental", trial.len()),
+            &trial,
+            move |b, t| {
+                b.iter_batched(
+                    || RandomizedIncremental::new(t.clone()).with_seed(TASK_4_SEED),
+                    |data| {
+                        data.find_closest_pair();
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
+        // Plane sweep
+        group.bench_with_input(
+            BenchmarkId::new("plane_sweep", trial.len()),
+            &trial,
+            move |b, t| {
+                b.iter_batched(
+                    || PlaneSweep::new(t.clone()),
+                    |mut data| {
+                        data.find_closest_pair();
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
+        // Approximate solver, within twice the closest distance
+        group.bench_with_input(
+            BenchmarkId::new("approximate_1", trial.len()),
+            &trial,
+            move |b, t| {
+                b.iter_batched(
+                    || Approximate::new(t.clone(), 1.0),
                     |data| {
                         data.find_closest_pair();
                     },
@@ -90,6 +177,55 @@ pub fn closest_pair_average(c: &mut Criterion) {
     }
 }
 
+/// The approximate solver against the plane sweep on millions of points, where the one sort of the
+/// approximate solver should beat the sort and sweep of the plane sweep.
+pub fn approximate_large(c: &mut Criterion) {
+    const SIZES: [usize; 2] = [1 << 20, 1 << 22];
+
+    let mut group = c.benchmark_group("approximate_large");
+    group.sample_size(10);
+    let mut rng = StdRng::seed_from_u64(0xA991_A26E);
+
+    for &distribution in [
+        generators::Distribution::Uniform,
+        generators::Distribution::Clustered,
+    ]
+    .iter()
+    {
+        for &size in SIZES.iter() {
+            let trial = distribution.generate(&mut rng, size);
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("plane_sweep/{}", distribution.name()), size),
+                &trial,
+                move |b, t| {
+                    b.iter_batched(
+                        || PlaneSweep::new(t.clone()),
+                        |mut data| {
+                            data.find_closest_pair();
+                        },
+                        BatchSize::LargeInput,
+                    );
+                },
+            );
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("approximate_1/{}", distribution.name()), size),
+                &trial,
+                move |b, t| {
+                    b.iter_batched(
+                        || Approximate::new(t.clone(), 1.0),
+                        |data| {
+                            data.find_closest_pair();
+                        },
+                        BatchSize::LargeInput,
+                    );
+                },
+            );
+        }
+    }
+}
+
 pub fn closest_pair_worst(c: &mut Criterion) {
     const ITER_MIN: usize = 5;
     const ITER_MAX: usize = 25;
@@ -97,16 +233,8 @@ pub fn closest_pair_worst(c: &mut Criterion) {
     let mut group = c.benchmark_group("closest_pair_worst");
     let mut rng = StdRng::seed_from_u64(0xDEAD_BEEF);
 
-    let trials = (ITER_MIN..=ITER_MAX).map(|x| {
-        let mut v = (0_usize..(FACTOR.powf(x as f32) as usize))
-            .map(|xv| Point {
-                x: xv as f64,
-                y: rng.gen::<i64>() as f64,
-            })
-            .collect_vec();
-        v.rotate_right(1);
-        v
-    });
+    let trials = (ITER_MIN..=ITER_MAX)
+        .map(|x| generators::quick_select_worst(&mut rng, FACTOR.powf(x as f32) as usize));
 
     for trial in trials {
         // Task 1
@@ -160,7 +288,7 @@ pub fn closest_pair_worst(c: &mut Criterion) {
             &trial,
             move |b, t| {
                 b.iter_batched(
-                    || Task4::new(t.clone()),
+                    || Task4::new(t.clone()).with_seed(TASK_4_SEED),
                     |data| {
                         data.find_closest_pair();
                     },
@@ -168,6 +296,51 @@ pub fn closest_pair_worst(c: &mut Criterion) {
                 );
             },
         );
+
+        // k-d tree: nearest neighbour of every point
+        group.bench_with_input(
+            BenchmarkId::new("kd_tree", trial.len()),
+            &trial,
+            move |b, t| {
+                b.iter_batched(
+                    || t.clone(),
+                    |data| {
+                        KdTree::new(data).closest_pair();
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
+        // Randomised incremental grid
+        group.bench_with_input(
+            BenchmarkId::new("randomized_incremental", trial.len()),
+            &trial,
+            move |b, t| {
+                b.iter_batched(
+                    || RandomizedIncremental::new(t.clone()).with_seed(TASK_4_SEED),
+                    |data| {
+                        data.find_closest_pair();
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
+        // Plane sweep
+        group.bench_with_input(
+            BenchmarkId::new("plane_sweep", trial.len()),
+            &trial,
+            move |b, t| {
+                b.iter_batched(
+                    || PlaneSweep::new(t.clone()),
+                    |mut data| {
+                        data.find_closest_pair();
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
     }
 }
 
@@ -176,30 +349,22 @@ pub fn quick_select_average(c: &mut Criterion) {
     let mut group = c.benchmark_group("quick_select_average");
     let mut rng = StdRng::seed_from_u64(0xC0DE_BEEF);
 
-    let trials = (20..=ITERATIONS).map(|x| {
-        (0_usize..(1.5_f32.powf(x as f32) as usize))
-            .map(|_| Point {
-                x: rng.gen::<i64>() as f64,
-                y: rng.gen::<i64>() as f64,
-            })
-            .collect_vec()
-    });
+    let trials =
+        (20..=ITERATIONS).map(|x| generators::uniform(&mut rng, 1.5_f32.powf(x as f32) as usize));
 
     for trial in trials {
         let m = trial.len() / 2;
-        group.bench_with_input(
-            BenchmarkId::from_parameter(trial.len()),
-            &trial,
-            move |b, t| {
+        for &(name, select) in SELECTS.iter() {
+            group.bench_with_input(BenchmarkId::new(name, trial.len()), &trial, move |b, t| {
                 b.iter_batched(
                     || t.clone(),
                     |mut data| {
-                        quick_select_points(&mut data, m);
+                        select(&mut data, m);
                     },
                     BatchSize::LargeInput,
                 );
-            },
-        );
+            });
+        }
     }
 }
 
@@ -208,27 +373,101 @@ pub fn quick_select_worst(c: &mut Criterion) {
     let mut rng = StdRng::seed_from_u64(0xC0DE_BEEF);
     let mut group = c.benchmark_group("quick_select_worst");
 
-    let trials = (15..=ITERATIONS).map(|x| {
-        let mut v = (0_usize..(1.5_f32.powf(x as f32) as usize))
-            .map(|xv| Point {
-                x: xv as f64,
-                y: rng.gen::<i64>() as f64,
-            })
-            .collect_vec();
-        v.rotate_right(1);
-        v
-    });
+    let trials = (15..=ITERATIONS)
+        .map(|x| generators::quick_select_worst(&mut rng, 1.5_f32.powf(x as f32) as usize));
 
     for trial in trials {
         let m = trial.len() / 2;
+        for &(name, select) in SELECTS.iter() {
+            group.bench_with_input(BenchmarkId::new(name, trial.len()), &trial, move |b, t| {
+                b.iter_batched(
+                    || t.clone(),
+                    |mut data| {
+                        select(&mut data, m);
+                    },
+                    BatchSize::LargeInput,
+                );
+            });
+        }
+    }
+}
+
+pub fn quick_select_duplicate_x(c: &mut Criterion) {
+    const ITERATIONS: usize = 27;
+    let mut rng = StdRng::seed_from_u64(0xC0DE_BEEF);
+    let mut group = c.benchmark_group("quick_select_duplicate_x");
+
+    // All points on one vertical line, and points on a few vertical lines
+    for &columns in [1, 8].iter() {
+        let trials = (20..=ITERATIONS)
+            .map(|x| generators::columns(&mut rng, 1.5_f32.powf(x as f32) as usize, columns));
+
+        for trial in trials {
+            let m = trial.len() / 2;
+            for &(name, select) in SELECTS.iter() {
+                group.bench_with_input(
+                    BenchmarkId::new(format!("{}/{}_columns", name, columns), trial.len()),
+                    &trial,
+                    move |b, t| {
+                        b.iter_batched(
+                            || t.clone(),
+                            |mut data| {
+                                select(&mut data, m);
+                            },
+                            BatchSize::LargeInput,
+                        );
+                    },
+                );
+            }
+        }
+    }
+}
+
+pub fn leaf_size(c: &mut Criterion) {
+    const SIZE: usize = 56_815;
+    const LEAF_SIZES: [usize; 10] = [3, 4, 6, 8, 12, 16, 24, 32, 48, 64];
+
+    let mut group = c.benchmark_group("leaf_size");
+    let mut rng = StdRng::seed_from_u64(0x1EAF_512E);
+    let trial = generators::uniform(&mut rng, SIZE);
+
+    for &leaf_size in LEAF_SIZES.iter() {
         group.bench_with_input(
-            BenchmarkId::from_parameter(trial.len()),
+            BenchmarkId::new("task_1", leaf_size),
             &trial,
             move |b, t| {
                 b.iter_batched(
-                    || t.clone(),
+                    || Task1::new(t.clone()).with_leaf_size(leaf_size),
+                    |mut data| {
+                        data.find_closest_pair();
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("task_3_sort_first", leaf_size),
+            &trial,
+            move |b, t| {
+                b.iter_batched(
+                    || Task3QuickSort::new(t.clone()).with_leaf_size(leaf_size),
                     |mut data| {
-                        quick_select_points(&mut data, m);
+                        data.find_closest_pair();
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("task_3_maintain_y", leaf_size),
+            &trial,
+            move |b, t| {
+                b.iter_batched(
+                    || Task3SortedY::new(t.clone()).with_leaf_size(leaf_size),
+                    |mut data| {
+                        data.find_closest_pair();
                     },
                     BatchSize::LargeInput,
                 );
@@ -237,17 +476,88 @@ pub fn quick_select_worst(c: &mut Criterion) {
     }
 }
 
-pub fn points_distance(c: &mut Criterion) {
-    let mut rng = StdRng::seed_from_u64(0xBAB5_EED5);
+/// Every solver on every distribution, at a few sizes. `Algorithm::Auto` is tuned from these.
+pub fn closest_pair_distributions(c: &mut Criterion) {
+    const SIZES: [usize; 4] = [10, 17, 24, 30];
 
-    let a = Point {
-        x: rng.gen::<i64>() as f64,
-        y: rng.gen::<i64>() as f64,
+    let mut group = c.benchmark_group("closest_pair_distributions");
+    let mut rng = StdRng::seed_from_u64(0xD157_2ED5);
+    let options = Options {
+        seed: Some(TASK_4_SEED),
+        ..Options::default()
     };
 
-    let b = Point {
-        x: rng.gen::<i64>() as f64,
-        y: rng.gen::<i64>() as f64,
+    for &distribution in generators::Distribution::ALL.iter() {
+        for &x in SIZES.iter() {
+            let trial = distribution.generate(&mut rng, FACTOR.powf(x as f32) as usize);
+
+            for &algorithm in Algorithm::ALL.iter() {
+                group.bench_with_input(
+                    BenchmarkId::new(
+                        format!("{}/{}", algorithm.name(), distribution.name()),
+                        trial.len(),
+                    ),
+                    &trial,
+                    move |b, t| {
+                        b.iter_batched(
+                            || t.clone(),
+                            |data| {
+                                algorithm.find_closest_pair_with(data, &options);
+                            },
+                            BatchSize::LargeInput,
+                        );
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Inserts every point into a [`DynamicClosestPair`], then removes one of the closest pair until
+/// half are left. The time per point should grow no faster than `log² n`, even when most points are
+/// copies or packed into clusters.
+pub fn dynamic_updates(c: &mut Criterion) {
+    const ITER_MIN: usize = 16;
+    const ITER_MAX: usize = 28;
+
+    let mut group = c.benchmark_group("dynamic_updates");
+    let mut rng = StdRng::seed_from_u64(0xD1A1_0C0D);
+
+    for &distribution in [
+        generators::Distribution::Duplicates,
+        generators::Distribution::Clustered,
+    ]
+    .iter()
+    {
+        for x in (ITER_MIN..=ITER_MAX).step_by(4) {
+            let trial = distribution.generate(&mut rng, FACTOR.powf(x as f32) as usize);
+            group.bench_with_input(
+                BenchmarkId::new(distribution.name(), trial.len()),
+                &trial,
+                |b, t| {
+                    b.iter(|| {
+                        let mut set = DynamicClosestPair::new();
+                        for &point in t {
+                            set.insert(point);
+                        }
+                        for _ in 0..t.len() / 2 {
+                            let (a, _) = set.closest_handles().unwrap();
+                            set.remove(a);
+                        }
+                        set.len()
+                    });
+                },
+            );
+        }
+    }
+}
+
+pub fn points_distance(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0xBAB5_EED5);
+
+    let (a, b) = match generators::uniform(&mut rng, 2)[..] {
+        [a, b] => (a, b),
+        _ => unreachable!(),
     };
 
     let mut group = c.benchmark_group("geometry");
@@ -260,7 +570,7 @@ pub fn points_distance(c: &mut Criterion) {
 criterion_group! {
     name = benches;
     config = Criterion::default().measurement_time(Duration::new(10, 0)).warm_up_time(Duration::new(3, 0)).sample_size(50);
-    targets = points_distance, quick_select_average, quick_select_worst, closest_pair_average, closest_pair_worst
+    targets = points_distance, quick_select_average, quick_select_worst, quick_select_duplicate_x, closest_pair_average, approximate_large, closest_pair_worst, closest_pair_distributions, leaf_size, dynamic_updates
 }
 
 criterion_main!(benches);
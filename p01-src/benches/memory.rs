@@ -0,0 +1,112 @@
+//! Reports the peak heap memory and runtime of a single run of each solver.
+//!
+//! Criterion only measures time, so this benchmark installs a counting global allocator and runs
+//! each solver once per input size. Memory is reported above what is live before the run, so the
+//! input itself is not counted.
+
+use closest_pairs::cli::resolve_threads;
+use closest_pairs::closest::task_4::TASK_4_SEED;
+use closest_pairs::closest::{Algorithm, Options};
+use closest_pairs::generators;
+use rand::prelude::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+const SIZES: [usize; 3] = [10_000, 100_000, 1_000_000];
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Forwards to the system allocator, counting the bytes live and the most ever live at once.
+struct CountingAllocator;
+
+impl CountingAllocator {
+    fn add(size: usize) {
+        let now = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(now, Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn remove(size: usize) {
+        ALLOCATED.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let p = System.alloc(layout);
+        if !p.is_null() {
+            Self::add(layout.size());
+        }
+        p
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::remove(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let p = System.realloc(ptr, layout, new_size);
+        if !p.is_null() {
+            Self::remove(layout.size());
+            Self::add(new_size);
+        }
+        p
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0x3E30_5EED);
+    let threads = resolve_threads(0);
+
+    println!(
+        "{:<24} {:>10} {:>14} {:>12} {:>12}",
+        "solver", "points", "peak (MiB)", "allocations", "time (ms)"
+    );
+
+    for &size in SIZES.iter() {
+        let points = generators::uniform(&mut rng, size);
+
+        let solvers = Algorithm::ALL
+            .iter()
+            .map(|&a| (a.name().to_string(), a, 1))
+            .chain(Some((
+                "task_4_parallel".to_string(),
+                Algorithm::Task4,
+                threads,
+            )));
+
+        for (name, algorithm, threads) in solvers {
+            let options = Options {
+                threads,
+                seed: Some(TASK_4_SEED),
+                ..Options::default()
+            };
+            let input = points.clone();
+
+            let before = ALLOCATED.load(Ordering::Relaxed);
+            PEAK.store(before, Ordering::Relaxed);
+            ALLOCATIONS.store(0, Ordering::Relaxed);
+
+            let start = Instant::now();
+            algorithm.find_closest_pair_with(input, &options);
+            let elapsed = start.elapsed();
+
+            let peak = PEAK.load(Ordering::Relaxed) - before;
+            println!(
+                "{:<24} {:>10} {:>14.2} {:>12} {:>12.2}",
+                name,
+                size,
+                peak as f64 / (1 << 20) as f64,
+                ALLOCATIONS.load(Ordering::Relaxed),
+                elapsed.as_secs_f64() * 1e3
+            );
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::closest::{Algorithm, Options};
+use crate::parse::parse_stdin;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// The output expected from any program given an input that does not follow the spec.
+pub const MALFORMED_INPUT: &str = "Incorrectly formatted input";
+
+/// A test case in the spec format: an input file `name.in` and the expected output `name.out`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Case {
+    /// Path of the input file.
+    pub input: PathBuf,
+    /// Path of the expected output file.
+    pub output: PathBuf,
+}
+
+impl Case {
+    /// The name of the case, which is the input path without the `.in` extension.
+    pub fn name(&self) -> String {
+        self.input.with_extension("").display().to_string()
+    }
+}
+
+/// Finds every test case under `dir`, including subdirectories, sorted by path. Input files
+/// without a matching output file are ignored.
+pub fn find_cases(dir: &Path) -> io::Result<Vec<Case>> {
+    let mut cases = Vec::new();
+
+    let mut entries = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            cases.extend(find_cases(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "in") {
+            let output = path.with_extension("out");
+            if output.is_file() {
+                cases.push(Case {
+                    input: path,
+                    output,
+                });
+            }
+        }
+    }
+    Ok(cases)
+}
+
+/// What to run a test case against.
+#[derive(Clone, Debug)]
+pub enum Runner {
+    /// Parse the input and run a solver in this process with some options.
+    Solver(Algorithm, Options),
+    /// Run an executable with some arguments, giving it the input on stdin.
+    Binary(PathBuf, Vec<String>),
+}
+
+impl Runner {
+    /// A human-readable name of the runner.
+    pub fn name(&self) -> String {
+        match self {
+            Runner::Solver(algorithm, _) => algorithm.name().to_string(),
+            Runner::Binary(path, _) => path.display().to_string(),
+        }
+    }
+
+    /// Runs the input and returns everything that would have been printed to stdout.
+    pub fn run(&self, input: &str) -> io::Result<String> {
+        match self {
+            Runner::Solver(algorithm, options) => Ok(solve(*algorithm, options, input)),
+            Runner::Binary(path, args) => {
+                let mut child = Command::new(path)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::inherit())
+                    .spawn()?;
+
+                // Every binary reads all of stdin before printing, so this cannot deadlock
+                child
+                    .stdin
+                    .take()
+                    .expect("stdin is piped")
+                    .write_all(input.as_bytes())?;
+
+                let output = child.wait_with_output()?;
+                Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+            }
+        }
+    }
+}
+
+/// Solves an input with a solver and returns what the spec binaries would print.
+pub fn solve(algorithm: Algorithm, options: &Options, input: &str) -> String {
+    match parse_stdin(input) {
+        Ok((_, points)) => format!(
+            "{}\n",
+            algorithm
+                .find_closest_pair_with(points, options)
+                .distance()
+                .to_spec_string()
+        ),
+        Err(_) => format!("{}\n", MALFORMED_INPUT),
+    }
+}
+
+/// The result of running a single test case.
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    /// The case that was run.
+    pub case: Case,
+    /// The contents of the expected output file.
+    pub expected: String,
+    /// What the runner printed.
+    pub actual: String,
+}
+
+impl Outcome {
+    /// Returns whether the output matched, ignoring trailing whitespace.
+    pub fn passed(&self) -> bool {
+        self.expected.trim_end() == self.actual.trim_end()
+    }
+
+    /// A line-by-line diff of the expected and actual output, with `-` marking expected lines
+    /// and `+` marking actual lines.
+    pub fn diff(&self) -> String {
+        let expected = self.expected.trim_end().lines().collect::<Vec<_>>();
+        let actual = self.actual.trim_end().lines().collect::<Vec<_>>();
+
+        let mut diff = String::new();
+        for i in 0..expected.len().max(actual.len()) {
+            match (expected.get(i), actual.get(i)) {
+                (Some(e), Some(a)) if e == a => diff.push_str(&format!(" {}\n", e)),
+                (e, a) => {
+                    if let Some(e) = e {
+                        diff.push_str(&format!("-{}\n", e));
+                    }
+                    if let Some(a) = a {
+                        diff.push_str(&format!("+{}\n", a));
+                    }
+                }
+            }
+        }
+        diff
+    }
+}
+
+/// Runs a single test case.
+pub fn check_case(case: &Case, runner: &Runner) -> io::Result<Outcome> {
+    let input = fs::read_to_string(&case.input)?;
+    let expected = fs::read_to_string(&case.output)?;
+    let actual = runner.run(&input)?;
+
+    Ok(Outcome {
+        case: case.clone(),
+        expected,
+        actual,
+    })
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// An error encountered while parsing command-line arguments.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArgError {
+    /// The flag is not recognised by the program.
+    Unknown(String),
+    /// The flag expects a value but none was given.
+    MissingValue(String),
+    /// The value given to a flag could not be parsed.
+    InvalidValue(String, String),
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgError::Unknown(flag) => write!(f, "unknown argument `{}`", flag),
+            ArgError::MissingValue(flag) => write!(f, "`--{}` expects a value", flag),
+            ArgError::InvalidValue(flag, value) => {
+                write!(f, "invalid value `{}` for `--{}`", value, flag)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArgError {}
+
+/// Command-line arguments, split into `--option value` pairs, `--switch`es and positional values.
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    options: HashMap<String, String>,
+    switches: Vec<String>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    /// Parse a list of arguments (excluding the program name).
+    ///
+    /// `options` lists the flags that take a value and `switches` lists the flags that do not.
+    /// Values may also be given in the form `--option=value`.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        options: &[&str],
+        switches: &[&str],
+    ) -> Result<Self, ArgError> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let flag = match arg.strip_prefix("--") {
+                Some(flag) => flag,
+                None => {
+                    parsed.positional.push(arg);
+                    continue;
+                }
+            };
+
+            let (name, inline_value) = match flag.find('=') {
+                Some(i) => (&flag[..i], Some(flag[i + 1..].to_string())),
+                None => (flag, None),
+            };
+
+            if options.contains(&name) {
+                let value = match inline_value {
+                    Some(v) => v,
+                    None => args
+                        .next()
+                        .ok_or_else(|| ArgError::MissingValue(name.to_string()))?,
+                };
+                parsed.options.insert(name.to_string(), value);
+            } else if switches.contains(&name) && inline_value.is_none() {
+                parsed.switches.push(name.to_string());
+            } else {
+                return Err(ArgError::Unknown(arg));
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Returns the value of an option parsed with `FromStr`, or `None` if it was not given.
+    pub fn value<T: FromStr>(&self, name: &str) -> Result<Option<T>, ArgError> {
+        self.value_with(name, |s| s.parse().ok())
+    }
+
+    /// Returns the value of an option parsed with a custom function, or `None` if it was not given.
+    pub fn value_with<T>(
+        &self,
+        name: &str,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> Result<Option<T>, ArgError> {
+        match self.options.get(name) {
+            Some(v) => parse(v)
+                .map(Some)
+                .ok_or_else(|| ArgError::InvalidValue(name.to_string(), v.clone())),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns whether a switch was given.
+    pub fn switch(&self, name: &str) -> bool {
+        self.switches.iter().any(|s| s == name)
+    }
+
+    /// Returns all positional arguments in the order they were given.
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+}
+
+/// Parses an unsigned integer in decimal or `0x`-prefixed hexadecimal. Underscores are ignored, so
+/// that values such as `0xABAB_BABA` or `10_000_000` can be copied from source code.
+pub fn parse_u64(s: &str) -> Option<u64> {
+    let s = s.replace('_', "");
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Resolves a `--threads` value, where `0` means one thread per available CPU.
+pub fn resolve_threads(threads: usize) -> usize {
+    match threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+}
+
+/// Returns the resolved `--threads` value of `args`, or `1` if it was not given.
+pub fn threads(args: &Args) -> Result<usize, ArgError> {
+    Ok(args.value::<usize>("threads")?.map_or(1, resolve_threads))
+}
+
+/// Parses arguments that may only hold `--threads N`, as the spec binaries take, and returns the
+/// resolved number of threads. Prints the error and quits with status 2 if the arguments are
+/// invalid.
+pub fn threads_or_exit(args: impl IntoIterator<Item = String>) -> usize {
+    Args::parse(args, &["threads"], &[])
+        .and_then(|args| threads(&args))
+        .unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            std::process::exit(2)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parse_options_and_switches() {
+        let parsed = Args::parse(
+            args("--count 10 input.in --verbose --seed=0x10"),
+            &["count", "seed"],
+            &["verbose"],
+        )
+        .unwrap();
+
+        assert_eq!(parsed.value::<usize>("count"), Ok(Some(10)));
+        assert_eq!(parsed.value_with("seed", parse_u64), Ok(Some(16)));
+        assert_eq!(parsed.value::<usize>("output"), Ok(None));
+        assert!(parsed.switch("verbose"));
+        assert_eq!(parsed.positional(), &["input.in".to_string()]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Args::parse(args("--nope"), &[], &[]).unwrap_err(),
+            ArgError::Unknown("--nope".to_string())
+        );
+        assert_eq!(
+            Args::parse(args("--count"), &["count"], &[]).unwrap_err(),
+            ArgError::MissingValue("count".to_string())
+        );
+
+        let parsed = Args::parse(args("--count ten"), &["count"], &[]).unwrap();
+        assert!(parsed.value::<usize>("count").is_err());
+    }
+
+    #[test]
+    fn parse_u64_hex_and_decimal() {
+        assert_eq!(parse_u64("0xABAB_BABA"), Some(0xABAB_BABA));
+        assert_eq!(parse_u64("10_000_000"), Some(10_000_000));
+        assert_eq!(parse_u64("0xZZ"), None);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::closest::sweep::PlaneSweep;
+use crate::geometry::{Point, PointPair};
+
+/// A pair of points at most `1 + epsilon` times as far apart as the closest pair, found with a
+/// sample and one sort.
+///
+/// The closest pair of an evenly spaced sample of the points, found with a [`PlaneSweep`], is at
+/// some distance `d` at least the closest distance, and is a good guess. It is close enough unless
+/// some pair is less than `r = d / (1 + epsilon)` apart. To rule that out, the points are sorted by
+/// mesh in a grid of meshes of size `r`, and every pair in neighbouring meshes is compared, which
+/// finds the closest pair exactly if it is closer than `r`.
+///
+/// The sample is a small fraction of the points, and the sort is a counting sort into bands of
+/// rows followed by a sort of each small band, so both take about `O(n)` time. When the sample is
+/// spread like the points, few pairs of points are closer than `d`, so each mesh holds a few points
+/// and the comparisons take `O(n)` time too. If the meshes hold too many points, or are too small
+/// to number, the closest pair is found exactly with a [`PlaneSweep`] instead.
+#[derive(Clone, Debug)]
+pub struct Approximate {
+    points: Vec<Point>,
+    epsilon: f64,
+}
+
+/// The key of a point's mesh, and the index of the point.
+type Keyed = (u64, usize);
+
+impl Approximate {
+    /// The spacing of the points in the sample, which holds one point in this many.
+    const STRIDE: usize = 32;
+    /// The number of pairs per point compared in the grid before falling back to the plane sweep.
+    const BUDGET: usize = 16;
+
+    /// Create a new `Approximate` struct that returns a pair at most `1 + epsilon` times as far
+    /// apart as the closest. An `epsilon` of zero finds the closest pair.
+    /// # Panics
+    /// Function will panic if `epsilon` is negative or not finite.
+    pub fn new(points: Vec<Point>, epsilon: f64) -> Self {
+        assert!(
+            epsilon >= 0.0 && epsilon.is_finite(),
+            "epsilon must be a finite non-negative number"
+        );
+        Self { points, epsilon }
+    }
+
+    /// The factor that the distance returned may be over the closest distance, less one.
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Find a pair of points at most `1 + epsilon` times as far apart as the closest pair.
+    /// # Panics
+    /// Function will panic if there are fewer than two points.
+    pub fn find_closest_pair(&self) -> PointPair {
+        assert!(self.points.len() >= 2, "at least two points are required");
+
+        if self.points.len() < 2 * Self::STRIDE {
+            return PlaneSweep::new(self.points.clone()).find_closest_pair();
+        }
+        let sample = self.points.iter().step_by(Self::STRIDE).copied().collect();
+        let guess = PlaneSweep::new(sample).find_closest_pair();
+        let d = guess.distance().0;
+        if d == 0.0 {
+            return guess;
+        }
+
+        let (low, high) = bounds(&self.points);
+        // Widen the meshes a little, as a point less than 2^31 meshes from `low` is placed up to
+        // 2^-21 of a mesh away by rounding, and a pair just closer than `r` could otherwise be two
+        // meshes apart
+        let size = d / (1.0 + self.epsilon) * (1.0 + 1.0 / 65536.0);
+        match closest_in_grid(&self.points, low, high, size) {
+            Some(Some(pair)) if pair.distance() < guess.distance() => pair,
+            Some(_) => guess,
+            None => PlaneSweep::new(self.points.clone()).find_closest_pair(),
+        }
+    }
+}
+
+/// Returns the smallest and largest coordinates of the points, as the corners of their bounding
+/// box.
+fn bounds(points: &[Point]) -> (Point, Point) {
+    points.iter().fold(
+        (
+            Point {
+                x: f64::INFINITY,
+                y: f64::INFINITY,
+            },
+            Point {
+                x: f64::NEG_INFINITY,
+                y: f64::NEG_INFINITY,
+            },
+        ),
+        |(low, high), p| {
+            (
+                Point {
+                    x: low.x.min(p.x),
+                    y: low.y.min(p.y),
+                },
+                Point {
+                    x: high.x.max(p.x),
+                    y: high.y.max(p.y),
+                },
+            )
+        },
+    )
+}
+
+/// Finds the closest pair among the points in neighbouring meshes of a grid of meshes of `size`.
+/// # Returns
+/// Function returns `None` if there are too many meshes to number, or too many pairs to compare.
+/// Otherwise it returns the closest pair compared, if any.
+fn closest_in_grid(
+    points: &[Point],
+    low: Point,
+    high: Point,
+    size: f64,
+) -> Option<Option<PointPair>> {
+    // Rows and columns must fit in half a key, with room for the one after the last
+    let limit = (1_u64 << 31) as f64;
+    if !((high.x - low.x) / size < limit && (high.y - low.y) / size < limit) {
+        return None;
+    }
+    let rows = ((high.y - low.y) / size) as u64 + 1;
+    let (mut keyed, ends) = band_by_row(points, low, size, rows);
+
+    let mut closest: Option<PointPair> = None;
+    let mut budget = Approximate::BUDGET * keyed.len();
+    let mut compare = |ps: &[Keyed], qs: &[Keyed]| {
+        budget = budget.checked_sub(ps.len() * qs.len())?;
+        for &(_, i) in ps {
+            for &(_, j) in qs {
+                let (p, q) = (points[i], points[j]);
+                if closest.is_none_or(|c| p.distance_to(q) < c.distance()) {
+                    closest = Some(PointPair(p, q));
+                }
+            }
+        }
+        Some(())
+    };
+
+    // Each mesh is compared with itself and the meshes after it: the next in its row, and the
+    // three below it in the next row, which a second index steps through alongside. The next row
+    // is in the same band or the one after, so each band is sorted by mesh just before the meshes
+    // of the band before are compared, while its keys are still in the cache
+    let mut below = 0;
+    let mut start = 0;
+    let mut band = 0;
+    let mut sorted = 0;
+    while start < keyed.len() {
+        while ends[band] <= start {
+            band += 1;
+        }
+        while sorted < ends.len() && sorted <= band + 1 {
+            let from = if sorted == 0 { 0 } else { ends[sorted - 1] };
+            keyed[from..ends[sorted]].sort_unstable_by_key(|&(key, _)| key);
+            sorted += 1;
+        }
+
+        let key = keyed[start].0;
+        let end = start + keyed[start..].iter().take_while(|k| k.0 == key).count();
+        let mesh = &keyed[start..end];
+        for (i, &point) in mesh.iter().enumerate() {
+            compare(&[point], &mesh[i + 1..])?;
+        }
+
+        let next = end + keyed[end..].iter().take_while(|k| k.0 == key + 1).count();
+        compare(mesh, &keyed[end..next])?;
+
+        let (row, column) = (key >> 32, key & 0xFFFF_FFFF);
+        let from = (row + 1) << 32 | column.saturating_sub(1);
+        let to = (row + 1) << 32 | (column + 1);
+        while below < keyed.len() && keyed[below].0 < from {
+            below += 1;
+        }
+        let last = below + keyed[below..].iter().take_while(|k| k.0 <= to).count();
+        compare(mesh, &keyed[below..last])?;
+
+        start = end;
+    }
+    Some(closest)
+}
+
+/// Returns the indices of the points keyed by mesh in a grid of meshes of `size` with `rows` rows,
+/// moved into bands of rows in order, and the end of each band.
+///
+/// There is about one band for every 32 points, so that each band fits in the cache and can be
+/// sorted quickly on its own, and the counts of the bands fit in the cache too.
+fn band_by_row(points: &[Point], low: Point, size: f64, rows: u64) -> (Vec<Keyed>, Vec<usize>) {
+    let row = |p: Point| ((p.y - low.y) / size) as u64;
+    let mut shift = 0;
+    let bands = (points.len() / 32).max(1) as u64;
+    while (rows - 1) >> shift >= bands {
+        shift += 1;
+    }
+
+    // Counted one band along, so that each band starts where the one before ends
+    let mut ends = vec![0; ((rows - 1) >> shift) as usize + 2];
+    for &p in points {
+        ends[(row(p) >> shift) as usize + 1] += 1;
+    }
+    for band in 1..ends.len() {
+        ends[band] += ends[band - 1];
+    }
+
+    let mut keyed = vec![(0, 0); points.len()];
+    for (i, &p) in points.iter().enumerate() {
+        let row = row(p);
+        let column = ((p.x - low.x) / size) as u64;
+        let end = &mut ends[(row >> shift) as usize];
+        keyed[*end] = (row << 32 | column, i);
+        *end += 1;
+    }
+    ends.pop();
+    (keyed, ends)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators;
+    use crate::verify::reference_closest_pair;
+    use rand::prelude::*;
+
+    /// Checks that the pair found is within `1 + epsilon` of the closest, returning whether it is
+    /// further than the closest.
+    fn check(points: &[Point], epsilon: f64) -> bool {
+        let closest = reference_closest_pair(points).distance().0;
+        let found = Approximate::new(points.to_vec(), epsilon)
+            .find_closest_pair()
+            .distance()
+            .0;
+        assert!(
+            found >= closest && found <= (1.0 + epsilon) * closest,
+            "{} points and epsilon {}: found {}, closest {}",
+            points.len(),
+            epsilon,
+            found,
+            closest
+        );
+        found > closest
+    }
+
+    #[test]
+    fn within_epsilon_of_closest() {
+        let mut rng = StdRng::seed_from_u64(0xE951_1011);
+        for &distribution in generators::Distribution::ALL.iter() {
+            for &size in &[2, 3, 100, 2_000] {
+                let points = distribution.generate(&mut rng, size);
+                for &epsilon in &[0.0, 0.01, 0.5, 1.0, 10.0] {
+                    check(&points, epsilon);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn within_epsilon_on_many_inputs() {
+        // On a jittered lattice many pairs are nearly as close as the closest, so the closest pair
+        // of the sample is often close enough without being the closest
+        let mut rng = StdRng::seed_from_u64(0x5EED_E951);
+        let mut inexact = 0;
+        for _ in 0..20 {
+            let points = generators::Distribution::Lattice.generate(&mut rng, 2_000);
+            for &epsilon in &[0.5, 1.0, 3.0, 10.0] {
+                if check(&points, epsilon) {
+                    inexact += 1;
+                }
+            }
+        }
+        assert!(inexact > 0);
+    }
+
+    #[test]
+    fn tiny_meshes_fall_back_to_the_sweep() {
+        // The closest pair is so close compared to the spread of the points that the meshes cannot
+        // be numbered
+        let mut rng = StdRng::seed_from_u64(0x7_1);
+        let mut points = generators::uniform(&mut rng, 1_000);
+        points[0] = Point { x: 0.0, y: 0.0 };
+        points[1] = Point { x: 1e-300, y: 0.0 };
+        check(&points, 0.5);
+
+        // Every point packed into a few meshes
+        let (low, high) = bounds(&points);
+        let size = (high.x - low.x).max(high.y - low.y);
+        assert!(closest_in_grid(&points, low, high, size).is_none());
+    }
+}
@@ -0,0 +1,197 @@
+use crate::closest::Algorithm;
+use crate::geometry::Point;
+use fnv::FnvHashMap;
+use std::fmt;
+
+/// Inputs with at most this many points are solved by [`Algorithm::Task3QuickSort`] without being
+/// sampled, as every solver takes a few microseconds on them.
+pub const TINY: usize = 64;
+
+/// Inputs with fewer points than this are solved by [`Algorithm::Task3QuickSort`] rather than
+/// [`Algorithm::PlaneSweep`]. In the `closest_pair_distributions` benchmark the sweep is slower at
+/// 985 points and faster at 16834 on every distribution the sweep is picked for.
+pub const SMALL: usize = 4_096;
+
+/// The number of points sampled for the shape of the input.
+pub const SHAPE_SAMPLES: usize = 256;
+
+/// The number of points checked for an exact duplicate. They are all checked in one pass over the
+/// input.
+pub const DUPLICATE_SAMPLES: usize = 256;
+
+/// Inputs where at least this fraction of the points checked have an exact duplicate are solved by
+/// [`Algorithm::Task4`].
+pub const MIN_DUPLICATES: f64 = 0.9;
+
+/// Inputs where fewer than this fraction of the sampled x-coordinates are distinct are treated as
+/// lying on a few vertical lines.
+pub const MIN_DISTINCT_X: f64 = 0.5;
+
+/// Inputs whose sample is less than this fraction as wide as it is tall are treated as a narrow
+/// vertical band.
+pub const MIN_ASPECT: f64 = 0.01;
+
+/// What [`Algorithm::Auto`] measures about an input to pick a solver.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Profile {
+    /// The number of points.
+    pub len: usize,
+    /// The fraction of the points checked that have an exact duplicate elsewhere in the input.
+    pub duplicates: f64,
+    /// The fraction of distinct x-coordinates among the points sampled.
+    pub distinct_x: f64,
+    /// The width of the bounding box of the points sampled, divided by its height.
+    pub aspect: f64,
+}
+
+impl Profile {
+    /// Measures an input from points spread evenly through it, so that the same input always gets
+    /// the same profile. Inputs of at most [`TINY`] points are not sampled, and get a profile of
+    /// just their length.
+    pub fn of(points: &[Point]) -> Self {
+        let mut profile = Profile {
+            len: points.len(),
+            duplicates: 0.0,
+            distinct_x: 1.0,
+            aspect: 1.0,
+        };
+        if points.len() <= TINY {
+            return profile;
+        }
+
+        // Each point checked is keyed by its coordinates, with the index of the first point
+        // checked at them and whether any other point is there
+        let key = |p: &Point| ((p.x + 0.0).to_bits(), (p.y + 0.0).to_bits());
+        let checked = sample(points.len(), DUPLICATE_SAMPLES).collect::<Vec<_>>();
+        let mut seen = FnvHashMap::default();
+        for &i in &checked {
+            seen.entry(key(&points[i])).or_insert((i, false));
+        }
+        for (j, p) in points.iter().enumerate() {
+            if let Some((i, duplicated)) = seen.get_mut(&key(p)) {
+                *duplicated |= *i != j;
+            }
+        }
+        let duplicated = checked
+            .iter()
+            .filter(|&&i| seen[&key(&points[i])].1)
+            .count();
+        profile.duplicates = duplicated as f64 / checked.len() as f64;
+
+        let sampled = sample(points.len(), SHAPE_SAMPLES)
+            .map(|i| points[i])
+            .collect::<Vec<_>>();
+        let mut xs = sampled.iter().map(|p| p.x).collect::<Vec<_>>();
+        xs.sort_unstable_by(f64::total_cmp);
+        xs.dedup();
+        profile.distinct_x = xs.len() as f64 / sampled.len() as f64;
+
+        let extent = |coordinate: fn(&Point) -> f64| {
+            let (low, high) = sampled
+                .iter()
+                .map(coordinate)
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), v| {
+                    (low.min(v), high.max(v))
+                });
+            high - low
+        };
+        profile.aspect = extent(|p| p.x) / extent(|p| p.y);
+        profile
+    }
+
+    /// Picks the solver that was fastest on benchmarked inputs most like this one.
+    ///
+    /// Task 4 returns as soon as its first random point has a duplicate, so it is by far the
+    /// fastest when nearly every point does. The plane sweep and `task_3_sort_first` both slow
+    /// down when many points share a narrow range of x, where `task_3_maintain_y` is fastest.
+    pub fn choose(&self) -> Algorithm {
+        if self.len <= TINY {
+            Algorithm::Task3QuickSort
+        } else if self.duplicates >= MIN_DUPLICATES {
+            Algorithm::Task4
+        } else if self.distinct_x < MIN_DISTINCT_X || self.aspect < MIN_ASPECT {
+            Algorithm::Task3SortedY
+        } else if self.len < SMALL {
+            Algorithm::Task3QuickSort
+        } else {
+            Algorithm::PlaneSweep
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} points, {:.0}% duplicated, {:.0}% distinct x, aspect {:.3e}",
+            self.len,
+            self.duplicates * 100.0,
+            self.distinct_x * 100.0,
+            self.aspect
+        )
+    }
+}
+
+/// Picks the solver for `points`. See [`Profile::choose`].
+pub fn choose(points: &[Point]) -> Algorithm {
+    Profile::of(points).choose()
+}
+
+/// The indices of `count` points spread evenly through a list of `len` points, or of every point
+/// if there are fewer.
+fn sample(len: usize, count: usize) -> impl Iterator<Item = usize> {
+    let count = count.min(len);
+    (0..count).map(move |i| i * len / count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::{self, Distribution};
+    use rand::prelude::*;
+
+    #[test]
+    fn choices_match_benchmarks() {
+        let mut rng = StdRng::seed_from_u64(0xA070);
+        let expected = [
+            (Distribution::Uniform, Algorithm::PlaneSweep),
+            (Distribution::Clustered, Algorithm::PlaneSweep),
+            (Distribution::Circle, Algorithm::PlaneSweep),
+            (Distribution::Duplicates, Algorithm::Task4),
+            (Distribution::Columns, Algorithm::Task3SortedY),
+            (Distribution::QuickSelectWorst, Algorithm::Task3SortedY),
+            (Distribution::StripWorst, Algorithm::Task3SortedY),
+        ];
+        for &(distribution, algorithm) in &expected {
+            let points = distribution.generate(&mut rng, 20_000);
+            assert_eq!(choose(&points), algorithm, "{}", distribution.name());
+        }
+
+        assert_eq!(
+            choose(&generators::uniform(&mut rng, 1_000)),
+            Algorithm::Task3QuickSort
+        );
+        assert_eq!(
+            choose(&generators::uniform(&mut rng, 2)),
+            Algorithm::Task3QuickSort
+        );
+    }
+
+    #[test]
+    fn counts_duplicates() {
+        let mut rng = StdRng::seed_from_u64(0xD0B);
+        let mut points = generators::uniform(&mut rng, 10_000);
+        assert_eq!(Profile::of(&points).duplicates, 0.0);
+
+        // Copy every other point over its neighbour, and one point to the end as negative zeros
+        for i in (0..points.len() / 2).step_by(2) {
+            points[i + 1] = points[i];
+        }
+        points[0] = Point { x: 0.0, y: 0.0 };
+        *points.last_mut().unwrap() = Point { x: -0.0, y: -0.0 };
+
+        let profile = Profile::of(&points);
+        assert!((0.4..0.6).contains(&profile.duplicates), "{}", profile);
+        assert_eq!(profile.choose(), Algorithm::PlaneSweep);
+    }
+}
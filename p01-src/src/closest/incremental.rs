@@ -0,0 +1,97 @@
+use crate::geometry::{Point, PointPair};
+use crate::spatial::Mesh;
+use rand::prelude::*;
+
+/// The randomised incremental grid algorithm of Golin, Raman, Schwarz and Smid (1995), after Rabin
+/// (1976).
+///
+/// Points are shuffled and added to a mesh whose meshes are the size of the closest distance so far,
+/// so that any closer pair is in neighbouring meshes. Whenever a point is closer to one already in
+/// the mesh, the mesh is rebuilt from the points so far at the new distance. The `i`th point only
+/// changes the closest pair with probability at most `2 / i`, so the rebuilds take `O(n)` expected
+/// time in total.
+#[derive(Clone, Debug)]
+pub struct RandomizedIncremental {
+    points: Vec<Point>,
+    seed: u64,
+}
+
+impl RandomizedIncremental {
+    /// Create a new `RandomizedIncremental` struct. The random number generator is seeded from the
+    /// operating system's entropy source, so that an input cannot be crafted against a known seed.
+    pub fn new(points: Vec<Point>) -> Self {
+        Self {
+            points,
+            seed: rand::random(),
+        }
+    }
+
+    /// Seed the random number generator with `seed`, so that runs can be reproduced.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The seed `find_closest_pair` uses.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Find the closest pair of points in the list.
+    /// # Panics
+    /// Function will panic if there are fewer than two points.
+    pub fn find_closest_pair(&self) -> PointPair {
+        self.find_closest_pair_with_rng(&mut StdRng::seed_from_u64(self.seed))
+    }
+
+    /// Find the closest pair of points in the list, shuffling the points with `rng` instead of the
+    /// seed.
+    /// # Panics
+    /// Function will panic if there are fewer than two points.
+    pub fn find_closest_pair_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> PointPair {
+        assert!(self.points.len() >= 2, "at least two points are required");
+
+        let mut points = self.points.clone();
+        points.shuffle(rng);
+
+        let mut closest = PointPair(points[0], points[1]);
+        let mut mesh = Mesh::default();
+        if !rebuild(&mut mesh, &points[..2], closest) {
+            return closest;
+        }
+
+        for i in 2..points.len() {
+            mesh.add_point_unchecked(points[i]);
+            match mesh.closest_pair_to_point_in_neighbour(points[i]) {
+                Some(pair) if pair.distance() < closest.distance() => {
+                    closest = pair;
+                    if !rebuild(&mut mesh, &points[..=i], closest) {
+                        return closest;
+                    }
+                }
+                _ => {}
+            }
+        }
+        closest
+    }
+}
+
+/// Replaces the contents of the mesh with `points`, in meshes the size of the distance of
+/// `closest`.
+/// # Returns
+/// Function returns `false` without rebuilding if the distance is zero, as nothing can be closer
+/// than a duplicate and a mesh of size zero cannot be built.
+fn rebuild(mesh: &mut Mesh, points: &[Point], closest: PointPair) -> bool {
+    let minimum = closest.distance().0;
+    if minimum == 0.0 {
+        return false;
+    }
+
+    // Widen the mesh by a few ulps, as a pair whose distance rounds down to exactly `minimum`
+    // could otherwise be two meshes apart
+    mesh.reset(minimum * (1.0 + 4.0 * f64::EPSILON));
+    for &point in points {
+        mesh.add_point_unchecked(point);
+    }
+    true
+}
@@ -1,58 +1,677 @@
-use itertools::Itertools;
+use std::panic;
+use std::str::FromStr;
+use std::thread;
 
 use crate::geometry::{Distance, Point, PointPair};
+use crate::kdtree::KdTree;
 
+pub mod approximate;
+pub mod auto;
+pub mod incremental;
+pub mod sweep;
 pub mod task_1;
 pub mod task_3_1;
 pub mod task_3_2;
 pub mod task_4;
 
+/// Divide-and-conquer steps on fewer points than this solve both halves on the same thread, as
+/// spawning a thread costs more than solving the half.
+pub const PARALLEL_CUTOFF: usize = 1 << 14;
+
+/// The smallest leaf size the divide-and-conquer solvers accept. A step on two points would split
+/// into two halves of one point, which have no pair to return.
+pub const MIN_LEAF_SIZE: usize = 3;
+
+/// Options shared by the solvers. A solver ignores any option that does not apply to it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// The maximum number of threads a solver may use. `1` solves everything on the calling
+    /// thread.
+    pub threads: usize,
+    /// The largest step a divide-and-conquer solver solves by brute force, or `None` for the
+    /// solver's own default.
+    pub leaf_size: Option<usize>,
+    /// The seed of a randomised solver, or `None` to draw a seed from the operating system's
+    /// entropy source.
+    pub seed: Option<u64>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            threads: 1,
+            leaf_size: None,
+            seed: None,
+        }
+    }
+}
+
+/// Solves the two halves of a divide-and-conquer step of `length` points.
+///
+/// If more than one thread is available and the step is large enough, `left` is run on a new
+/// scoped thread while `right` runs on the current one. Each half is given its share of the
+/// `threads` to pass on to its own recursive calls. The halves are split the same way however many
+/// threads are used, so the result does not depend on `threads`.
+pub(crate) fn join<A, B>(
+    threads: usize,
+    length: usize,
+    left: impl FnOnce(usize) -> A + Send,
+    right: impl FnOnce(usize) -> B,
+) -> (A, B)
+where
+    A: Send,
+{
+    if threads < 2 || length < PARALLEL_CUTOFF {
+        return (left(1), right(1));
+    }
+
+    let left_threads = threads / 2;
+    thread::scope(|s| {
+        let handle = s.spawn(move || left(left_threads));
+        let b = right(threads - left_threads);
+        let a = handle.join().unwrap_or_else(|e| panic::resume_unwind(e));
+        (a, b)
+    })
+}
+
+/// Like [`map_chunks`], but each chunk is given mutably.
+pub(crate) fn map_chunks_mut<T, R>(
+    threads: usize,
+    items: &mut [T],
+    f: impl Fn(usize, &mut [T]) -> R + Sync,
+) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+{
+    if threads < 2 || items.len() < PARALLEL_CUTOFF {
+        return vec![f(0, items)];
+    }
+
+    let chunk_size = items.len().div_ceil(threads);
+    let f = &f;
+    thread::scope(|s| {
+        let handles = items
+            .chunks_mut(chunk_size)
+            .enumerate()
+            .map(|(i, chunk)| s.spawn(move || f(i * chunk_size, chunk)))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
+/// Splits `items` into one chunk per thread and maps each chunk on its own scoped thread,
+/// returning the results in the order of the chunks. `f` is given the index of the first item of
+/// its chunk.
+///
+/// Lists with fewer than `PARALLEL_CUTOFF` items are mapped as a single chunk on the current
+/// thread.
+pub(crate) fn map_chunks<T, R>(
+    threads: usize,
+    items: &[T],
+    f: impl Fn(usize, &[T]) -> R + Sync,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    if threads < 2 || items.len() < PARALLEL_CUTOFF {
+        return vec![f(0, items)];
+    }
+
+    let chunk_size = items.len().div_ceil(threads);
+    let f = &f;
+    thread::scope(|s| {
+        let handles = items
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(i, chunk)| s.spawn(move || f(i * chunk_size, chunk)))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
+/// A closest pair algorithm, used to select a solver at runtime.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// See [`task_1::Task1`].
+    Task1,
+    /// See [`task_3_1::Task3QuickSort`].
+    Task3QuickSort,
+    /// See [`task_3_2::Task3SortedY`].
+    Task3SortedY,
+    /// See [`task_4::Task4`].
+    Task4,
+    /// See [`KdTree::closest_pair`].
+    KdTree,
+    /// See [`incremental::RandomizedIncremental`].
+    RandomizedIncremental,
+    /// See [`sweep::PlaneSweep`].
+    PlaneSweep,
+    /// Samples the input and runs the solver picked by [`auto::Profile::choose`].
+    Auto,
+}
+
+impl Algorithm {
+    /// All algorithms, in the order of the tasks in the spec and then the solvers beyond it.
+    pub const ALL: [Algorithm; 8] = [
+        Algorithm::Task1,
+        Algorithm::Task3QuickSort,
+        Algorithm::Task3SortedY,
+        Algorithm::Task4,
+        Algorithm::KdTree,
+        Algorithm::RandomizedIncremental,
+        Algorithm::PlaneSweep,
+        Algorithm::Auto,
+    ];
+
+    /// The name of the algorithm, as used in the benchmarks and accepted by `FromStr`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Task1 => "task_1",
+            Algorithm::Task3QuickSort => "task_3_sort_first",
+            Algorithm::Task3SortedY => "task_3_maintain_y",
+            Algorithm::Task4 => "task_4",
+            Algorithm::KdTree => "kd_tree",
+            Algorithm::RandomizedIncremental => "randomized_incremental",
+            Algorithm::PlaneSweep => "plane_sweep",
+            Algorithm::Auto => "auto",
+        }
+    }
+
+    /// Find the closest pair of points in the list using this algorithm with the default options.
+    /// # Panics
+    /// Function will panic if there are fewer than two points.
+    pub fn find_closest_pair(self, points: Vec<Point>) -> PointPair {
+        self.find_closest_pair_with(points, &Options::default())
+    }
+
+    /// Find the closest pair of points in the list using this algorithm.
+    /// # Panics
+    /// Function will panic if there are fewer than two points.
+    pub fn find_closest_pair_with(self, points: Vec<Point>, options: &Options) -> PointPair {
+        match self {
+            Algorithm::Task1 => task_1::Task1::new(points)
+                .with_threads(options.threads)
+                .with_leaf_size(
+                    options
+                        .leaf_size
+                        .unwrap_or(task_1::Task1::DEFAULT_LEAF_SIZE),
+                )
+                .find_closest_pair(),
+            Algorithm::Task3QuickSort => task_3_1::Task3QuickSort::new(points)
+                .with_threads(options.threads)
+                .with_leaf_size(
+                    options
+                        .leaf_size
+                        .unwrap_or(task_3_1::Task3QuickSort::DEFAULT_LEAF_SIZE),
+                )
+                .find_closest_pair(),
+            Algorithm::Task3SortedY => task_3_2::Task3SortedY::new(points)
+                .with_threads(options.threads)
+                .with_leaf_size(
+                    options
+                        .leaf_size
+                        .unwrap_or(task_3_2::Task3SortedY::DEFAULT_LEAF_SIZE),
+                )
+                .find_closest_pair(),
+            Algorithm::Task4 => {
+                let mut solver = task_4::Task4::new(points).with_threads(options.threads);
+                if let Some(seed) = options.seed {
+                    solver = solver.with_seed(seed);
+                }
+                solver.find_closest_pair()
+            }
+            Algorithm::KdTree => KdTree::new(points)
+                .closest_pair()
+                .expect("at least two points are needed"),
+            Algorithm::RandomizedIncremental => {
+                let mut solver = incremental::RandomizedIncremental::new(points);
+                if let Some(seed) = options.seed {
+                    solver = solver.with_seed(seed);
+                }
+                solver.find_closest_pair()
+            }
+            Algorithm::PlaneSweep => sweep::PlaneSweep::new(points).find_closest_pair(),
+            Algorithm::Auto => auto::choose(&points).find_closest_pair_with(points, options),
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Algorithm::ALL
+            .iter()
+            .copied()
+            .find(|a| a.name() == s)
+            .ok_or(())
+    }
+}
+
+/// The most points after any point in a strip that can be closer to it than the current minimum.
+///
+/// Points in each half are at least the current minimum apart, so a `minimum` by `2 * minimum`
+/// rectangle of the strip holds at most four points from each half: the point itself and seven
+/// others.
+pub const STRIP_NEIGHBOURS: usize = 7;
+
+/// Copies the points within the x-distance `current_minimum` of the `median` into `buffer`, in
+/// order, and returns the filled part of the buffer.
+/// # Panics
+/// Function will panic if `buffer` is shorter than `points`.
+pub fn fill_strip<'a>(
+    points: &[Point],
+    median: Point,
+    current_minimum: Distance,
+    buffer: &'a mut [Point],
+) -> &'a mut [Point] {
+    let mut length = 0;
+    for p in points {
+        if (p.x - median.x).abs() < current_minimum.0 {
+            buffer[length] = *p;
+            length += 1;
+        }
+    }
+    &mut buffer[..length]
+}
+
 /// Finds the minimum distance in a 'strip' of distances.
 ///
-/// This strip is defined as an iterator of
+/// This strip is defined as a slice of
 /// all the points within the x-distance of the current median, sorted by their y-coordinates.
-/// Because of the nature of the divide-and-conquer algorithm, it is proven that this the loop
-/// runs at most six times.
+/// Each point is only compared to the points after it that are within the y-distance of the
+/// current minimum, and to at most [`STRIP_NEIGHBOURS`] of them.
 /// # Returns
 /// Function returns `None` if there are no points better than the `current_distance` provided.
-pub fn find_minimum_in_strip<'a>(
-    points: impl Iterator<Item = &'a Point> + Clone,
-    current_minimum: Distance,
-) -> Option<PointPair> {
+pub fn find_minimum_in_strip(strip: &[Point], current_minimum: Distance) -> Option<PointPair> {
     let mut minimum_distance = current_minimum;
     let mut minimum_pair: Option<PointPair> = None;
 
-    // Sub-iter is a copy of the iterators in the strip
-    let mut sub_iter = points.clone().fuse();
-
-    for (a, b) in points.flat_map(move |x| {
-        // Do not take the item we're on (skip 1)
-        sub_iter.next();
-        // Repeat the current point, plus the next N items until out of bounds of strip
-        std::iter::repeat(x).zip(
-            sub_iter
-                .clone()
-                .take_while(move |&&p| (p.y - x.y) < minimum_distance.0),
-        )
-    }) {
-        if a.distance_to(*b) < minimum_distance {
-            minimum_distance = a.distance_to(*b);
-            minimum_pair = Some(PointPair(*a, *b));
+    for (i, a) in strip.iter().enumerate() {
+        for b in strip[i + 1..].iter().take(STRIP_NEIGHBOURS) {
+            if b.y - a.y >= minimum_distance.0 {
+                break;
+            }
+            let d = a.distance_to(*b);
+            if d < minimum_distance {
+                minimum_distance = d;
+                minimum_pair = Some(PointPair(*a, *b));
+            }
         }
     }
     minimum_pair
 }
 
-/// Performs a brute-force calculation to find the closest pair of points within the iterator.
+/// Performs a brute-force calculation to find the closest pair of points within the slice.
 ///
-/// This will run in `O(n^2)` time, so it is only used for the base case of the recursive algorithm.
+/// This will run in `O(n^2)` time without allocating, so it is only used for the base case of the
+/// recursive algorithm. Of equally close pairs, the first in the slice is returned.
 /// # Panics
-/// Function will panic if the iterator is empty.
-pub fn find_minimum_bruteforce<'a>(points: impl Iterator<Item = &'a Point> + Clone) -> PointPair {
-    points
-        .combinations(2)
-        .map(|x| PointPair(*x[0], *x[1]))
-        .sorted_by(|p1, p2| p1.distance().partial_cmp(&p2.distance()).unwrap())
-        .next()
-        .unwrap()
+/// Function will panic if there are fewer than two points.
+pub fn find_minimum_bruteforce(points: &[Point]) -> PointPair {
+    assert!(points.len() >= 2, "at least two points are required");
+
+    let mut minimum = PointPair(points[0], points[1]);
+    let mut minimum_distance = minimum.distance();
+    for (i, a) in points.iter().enumerate() {
+        for b in &points[i + 1..] {
+            let d = a.distance_to(*b);
+            if d < minimum_distance {
+                minimum_distance = d;
+                minimum = PointPair(*a, *b);
+            }
+        }
+    }
+    minimum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::closest::task_4::TASK_4_SEED;
+    use crate::generators;
+    use crate::verify::reference_closest_pair;
+    use rand::prelude::*;
+
+    /// Input sizes to test, from the smallest valid input up to 10^4.
+    const SIZES: [usize; 15] = [
+        2, 3, 4, 5, 6, 7, 8, 13, 32, 100, 257, 1_000, 2_049, 5_000, 10_000,
+    ];
+
+    /// Number of seeds tried for each size. Large inputs are slow to check against brute force, so
+    /// they get fewer seeds.
+    fn seeds_for(size: usize) -> u64 {
+        match size {
+            0..=10 => 50,
+            11..=300 => 10,
+            301..=2_000 => 3,
+            _ => 1,
+        }
+    }
+
+    /// The seed used to generate an input of `size` points on the `trial`th attempt.
+    fn seed_for(size: usize, trial: u64) -> u64 {
+        ((size as u64) << 16) | trial
+    }
+
+    /// A solver under test.
+    type Solver = (Algorithm, Options);
+
+    /// Every solver under test. The parallel variant of Task 4 uses a different sieve, so it is
+    /// tested separately; it runs on one thread below `PARALLEL_CUTOFF`. The divide-and-conquer
+    /// solvers are also tested with the smallest leaf size, which recurses the deepest.
+    fn solvers() -> Vec<Solver> {
+        let options = Options {
+            seed: Some(TASK_4_SEED),
+            ..Options::default()
+        };
+        let mut solvers = Algorithm::ALL
+            .iter()
+            .map(|&a| (a, options))
+            .collect::<Vec<_>>();
+        solvers.push((
+            Algorithm::Task4,
+            Options {
+                threads: 2,
+                ..options
+            },
+        ));
+        for &algorithm in &[
+            Algorithm::Task1,
+            Algorithm::Task3QuickSort,
+            Algorithm::Task3SortedY,
+        ] {
+            let options = Options {
+                leaf_size: Some(MIN_LEAF_SIZE),
+                ..Options::default()
+            };
+            solvers.push((algorithm, options));
+        }
+        solvers
+    }
+
+    /// Returns whether the solver fails to find the `expected` distance on `points`.
+    fn fails_with(solver: Solver, points: &[Point], expected: Distance) -> bool {
+        let (algorithm, options) = solver;
+        let run = std::panic::catch_unwind(|| {
+            algorithm.find_closest_pair_with(points.to_vec(), &options)
+        });
+        !matches!(run, Ok(found) if found.distance() == expected)
+    }
+
+    /// Returns whether the solver disagrees with brute force on `points`.
+    fn fails(solver: Solver, points: &[Point]) -> bool {
+        fails_with(solver, points, reference_closest_pair(points).distance())
+    }
+
+    /// Shrinks a failing input by repeatedly removing chunks of points while it still fails.
+    fn shrink(solver: Solver, mut points: Vec<Point>) -> Vec<Point> {
+        let mut chunk = points.len() / 2;
+        while chunk > 0 {
+            let mut start = 0;
+            while start < points.len() && points.len() > 2 {
+                let end = (start + chunk).min(points.len());
+                let mut candidate = points.clone();
+                candidate.drain(start..end);
+                if candidate.len() >= 2 && fails(solver, &candidate) {
+                    points = candidate;
+                } else {
+                    start = end;
+                }
+            }
+            chunk /= 2;
+        }
+        points
+    }
+
+    /// Checks every solver against brute force on inputs from `generate`. On failure, the
+    /// smallest failing size and its seed are reported for each solver, along with a shrunk
+    /// input that still fails.
+    fn check_against_bruteforce(name: &str, generate: impl Fn(&mut StdRng, usize) -> Vec<Point>) {
+        let mut failures: Vec<(Solver, usize, u64, Vec<Point>)> = Vec::new();
+
+        for &size in SIZES.iter() {
+            for trial in 0..seeds_for(size) {
+                let seed = seed_for(size, trial);
+                let points = generate(&mut StdRng::seed_from_u64(seed), size);
+                let expected = reference_closest_pair(&points).distance();
+
+                for solver in solvers() {
+                    // Only the first (smallest) failure of each solver is reported
+                    if failures.iter().any(|f| f.0 == solver) {
+                        continue;
+                    }
+                    if fails_with(solver, &points, expected) {
+                        failures.push((solver, size, seed, points.clone()));
+                    }
+                }
+            }
+        }
+
+        let report = failures
+            .into_iter()
+            .map(|(solver, size, seed, points)| {
+                format!(
+                    "{} ({} threads) on {}: fails with {} points from seed {:#x}; shrunk input: {:?}",
+                    solver.0.name(),
+                    solver.1.threads,
+                    name,
+                    size,
+                    seed,
+                    shrink(solver, points)
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert!(report.is_empty(), "\n{}\n", report.join("\n"));
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let options = |threads| Options {
+            threads,
+            ..Options::default()
+        };
+        let mut rng = StdRng::seed_from_u64(0x7EAD);
+        let points = generators::uniform(&mut rng, PARALLEL_CUTOFF * 3 + 1);
+
+        for &algorithm in Algorithm::ALL.iter() {
+            let sequential = algorithm.find_closest_pair(points.clone());
+            for &threads in [2, 3, 8].iter() {
+                // The parallel sieve, and any solver without a fixed seed, may pick a different
+                // pair when there are ties
+                if matches!(
+                    algorithm,
+                    Algorithm::Task4 | Algorithm::RandomizedIncremental
+                ) {
+                    let parallel =
+                        algorithm.find_closest_pair_with(points.clone(), &options(threads));
+                    assert_eq!(parallel.distance(), sequential.distance());
+                    continue;
+                }
+
+                let parallel = algorithm.find_closest_pair_with(points.clone(), &options(threads));
+                assert_eq!(
+                    (parallel.0, parallel.1),
+                    (sequential.0, sequential.1),
+                    "{} with {} threads",
+                    algorithm.name(),
+                    threads
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn strip_finds_pairs_across_median() {
+        let mut rng = StdRng::seed_from_u64(0x5781);
+        for &distribution in generators::Distribution::ALL.iter() {
+            for _ in 0..20 {
+                let mut points = distribution.generate(&mut rng, 200);
+                points.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+
+                let (left, right) = points.split_at(points.len() / 2);
+                let minimum = reference_closest_pair(left)
+                    .min(reference_closest_pair(right))
+                    .distance();
+
+                let mut buffer = points.clone();
+                let strip = fill_strip(&points, right[0], minimum, &mut buffer);
+                strip.sort_by(|a, b| a.y.partial_cmp(&b.y).unwrap());
+
+                let found = find_minimum_in_strip(strip, minimum).map_or(minimum, |p| p.distance());
+                assert_eq!(
+                    found,
+                    reference_closest_pair(&points).distance(),
+                    "{}",
+                    distribution.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn task_4_seeding() {
+        let mut rng = StdRng::seed_from_u64(0x5EED);
+        let points = generators::gaussian_clusters(&mut rng, 2_000, 4, 1e3);
+        let expected = reference_closest_pair(&points).distance();
+
+        for &threads in [1, 2].iter() {
+            let solver = |seed| {
+                task_4::Task4::new(points.clone())
+                    .with_threads(threads)
+                    .with_seed(seed)
+            };
+
+            // The same seed, or an RNG seeded with it, picks the same points
+            let first = solver(TASK_4_SEED).find_closest_pair();
+            let again = solver(TASK_4_SEED).find_closest_pair();
+            let with_rng =
+                solver(0).find_closest_pair_with_rng(&mut StdRng::seed_from_u64(TASK_4_SEED));
+            assert_eq!((first.0, first.1), (again.0, again.1));
+            assert_eq!((first.0, first.1), (with_rng.0, with_rng.1));
+
+            for seed in 0..20 {
+                assert_eq!(solver(seed).find_closest_pair().distance(), expected);
+            }
+            assert_eq!(
+                task_4::Task4::new(points.clone())
+                    .find_closest_pair_with_rng(&mut rng)
+                    .distance(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn randomized_near_duplicates_far_out() {
+        // Points across the whole range `make_points` generates, but with the closest pair nearly
+        // duplicates, so that Task 4 ends with meshes far smaller than the spread of the points.
+        // With a gap of 1e-300, the meshes of most points are beyond the range of an index.
+        for &gap in [1e-3, 1e-300].iter() {
+            check_against_bruteforce(&format!("near duplicates {:e} apart", gap), |rng, n| {
+                let mut points = generators::uniform(rng, n);
+                points[0] = Point { x: 0.0, y: 0.0 };
+                points[n - 1] = Point { x: gap, y: gap };
+                points
+            });
+        }
+    }
+
+    #[test]
+    fn randomized_generators() {
+        for &distribution in generators::Distribution::ALL.iter() {
+            check_against_bruteforce(distribution.name(), |rng, n| distribution.generate(rng, n));
+        }
+    }
+
+    #[test]
+    fn randomized_small_integers() {
+        // Many exact duplicates and ties
+        check_against_bruteforce("small integers", |rng, n| {
+            (0..n)
+                .map(|_| Point {
+                    x: rng.gen_range(-3..=3) as f64,
+                    y: rng.gen_range(-3..=3) as f64,
+                })
+                .collect()
+        });
+    }
+
+    #[test]
+    fn randomized_all_duplicates() {
+        check_against_bruteforce("all duplicates", |rng, n| {
+            let p = Point {
+                x: rng.gen::<i32>() as f64,
+                y: rng.gen::<i32>() as f64,
+            };
+            vec![p; n]
+        });
+    }
+
+    #[test]
+    fn randomized_collinear() {
+        check_against_bruteforce("vertical line", |rng, n| {
+            (0..n)
+                .map(|_| Point {
+                    x: 7.0,
+                    y: rng.gen::<i64>() as f64,
+                })
+                .collect()
+        });
+        check_against_bruteforce("horizontal line", |rng, n| {
+            (0..n)
+                .map(|_| Point {
+                    x: rng.gen::<i64>() as f64,
+                    y: -7.0,
+                })
+                .collect()
+        });
+    }
+
+    #[test]
+    fn randomized_negative_zeros() {
+        check_against_bruteforce("negative zeros", |rng, n| {
+            let coordinates = [0.0, -0.0, 1.0, -1.0, f64::MIN_POSITIVE, -f64::MIN_POSITIVE];
+            (0..n)
+                .map(|_| Point {
+                    x: *coordinates.choose(rng).unwrap(),
+                    y: *coordinates.choose(rng).unwrap(),
+                })
+                .collect()
+        });
+    }
+
+    #[test]
+    fn randomized_huge_magnitudes() {
+        // Large enough that squared distances approach the maximum f64, but never overflow
+        check_against_bruteforce("huge magnitudes", |rng, n| {
+            (0..n)
+                .map(|_| Point {
+                    x: rng.gen_range(-1e150..1e150),
+                    y: rng.gen_range(-1e150..1e150),
+                })
+                .collect()
+        });
+    }
+
+    #[test]
+    fn randomized_tiny_magnitudes() {
+        check_against_bruteforce("tiny magnitudes", |rng, n| {
+            (0..n)
+                .map(|_| Point {
+                    x: rng.gen_range(-1e-150..1e-150),
+                    y: rng.gen_range(-1e-150..1e-150),
+                })
+                .collect()
+        });
+    }
 }
@@ -0,0 +1,99 @@
+use crate::geometry::{Point, PointPair};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+/// A point ordered by its y-coordinate and then its x-coordinate, with `f64::total_cmp`, so that it
+/// can be kept in a `BTreeSet`.
+#[derive(Copy, Clone, Debug)]
+struct ByY(Point);
+
+impl PartialEq for ByY {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ByY {}
+
+impl PartialOrd for ByY {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ByY {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .y
+            .total_cmp(&other.0.y)
+            .then(self.0.x.total_cmp(&other.0.x))
+    }
+}
+
+/// The plane sweep of Shamos and Hoey (1975), as described by Hinrichs, Nievergelt and Schorn
+/// (1988).
+///
+/// Points are swept from left to right. The points less than the closest distance so far to the
+/// left of the sweep line are kept in a set ordered by y, so each point is only compared to the
+/// few of them within that distance above or below it. This takes `O(n log n)` time.
+#[derive(Clone, Debug)]
+pub struct PlaneSweep {
+    points: Vec<Point>,
+}
+
+impl PlaneSweep {
+    /// Create a new `PlaneSweep` struct.
+    pub fn new(points: Vec<Point>) -> Self {
+        Self { points }
+    }
+
+    /// Find the closest pair of points in the list.
+    /// # Panics
+    /// Function will panic if there are fewer than two points.
+    pub fn find_closest_pair(&mut self) -> PointPair {
+        assert!(self.points.len() >= 2, "at least two points are required");
+
+        let points = &mut self.points;
+        points.sort_unstable_by(|a, b| a.x.total_cmp(&b.x));
+
+        let mut closest = PointPair(points[0], points[1]);
+        let mut minimum = closest.distance().0;
+        // Nothing can be closer than a duplicate, which also could not be added to the set
+        if minimum == 0.0 {
+            return closest;
+        }
+        let mut active = BTreeSet::new();
+        let mut left = 0;
+
+        for &point in points.iter() {
+            // The computed distance of a pair is never less than the computed difference of
+            // either coordinate, so points at least `minimum` to the left can never be closer
+            while point.x - points[left].x >= minimum {
+                active.remove(&ByY(points[left]));
+                left += 1;
+            }
+
+            let from = ByY(Point {
+                x: f64::NEG_INFINITY,
+                y: point.y - minimum,
+            });
+            let to = ByY(Point {
+                x: f64::INFINITY,
+                y: point.y + minimum,
+            });
+            for &ByY(other) in active.range(from..=to) {
+                let d = point.distance_to(other).0;
+                if d < minimum {
+                    minimum = d;
+                    closest = PointPair(other, point);
+                }
+            }
+
+            if minimum == 0.0 {
+                break;
+            }
+            active.insert(ByY(point));
+        }
+        closest
+    }
+}
@@ -1,5 +1,3 @@
-use itertools::Itertools;
-
 use crate::closest;
 use crate::geometry::{Point, PointPair};
 use crate::quick_select::quick_select_points;
@@ -8,26 +6,57 @@ use crate::quick_select::quick_select_points;
 #[derive(Clone, Debug)]
 pub struct Task1 {
     points: Vec<Point>,
+    threads: usize,
+    leaf_size: usize,
 }
 
 impl Task1 {
+    /// The leaf size used unless [`with_leaf_size`](Self::with_leaf_size) is called.
+    pub const DEFAULT_LEAF_SIZE: usize = 48;
+
     /// Create a new `Task1` struct.
     pub fn new(points: Vec<Point>) -> Self {
-        Self { points }
+        Self {
+            points,
+            threads: 1,
+            leaf_size: Self::DEFAULT_LEAF_SIZE,
+        }
+    }
+
+    /// Solve the two halves of large divide steps concurrently, using up to `threads` threads.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Solve steps of at most `leaf_size` points by brute force. Values below
+    /// [`MIN_LEAF_SIZE`](crate::closest::MIN_LEAF_SIZE) are raised to it.
+    pub fn with_leaf_size(mut self, leaf_size: usize) -> Self {
+        self.leaf_size = leaf_size.max(closest::MIN_LEAF_SIZE);
+        self
     }
 
     /// Find the closest pair of points in the list.
     pub fn find_closest_pair(&mut self) -> PointPair {
-        find_closest_pair_inner(&mut self.points)
+        // The only allocation: every level builds its strip in its part of this buffer
+        let mut strip = self.points.clone();
+
+        find_closest_pair_inner(&mut self.points, &mut strip, self.threads, self.leaf_size)
     }
 }
 
-fn find_closest_pair_inner(points: &mut [Point]) -> PointPair {
+/// Finds the closest pair in `points`. `strip` is a buffer of the same length.
+fn find_closest_pair_inner(
+    points: &mut [Point],
+    strip: &mut [Point],
+    threads: usize,
+    leaf_size: usize,
+) -> PointPair {
     // Reference: W2 L3
 
     // Base case: we can't recurse any further
-    if points.len() <= 3 {
-        return closest::find_minimum_bruteforce(points.iter());
+    if points.len() <= leaf_size {
+        return closest::find_minimum_bruteforce(points);
     }
 
     // Use quickselect to find median point
@@ -35,20 +64,22 @@ fn find_closest_pair_inner(points: &mut [Point]) -> PointPair {
     let length = points.len();
     let (left, right) = quick_select_points(points, length / 2);
     let median = *right.first().unwrap();
+    let (left_strip, right_strip) = strip.split_at_mut(length / 2);
 
     // Recursively solve the problem by left and right
-    let left_minimum = find_closest_pair_inner(left);
-
-    let right_minimum = find_closest_pair_inner(right);
+    let (left_minimum, right_minimum) = closest::join(
+        threads,
+        length,
+        |t| find_closest_pair_inner(left, left_strip, t, leaf_size),
+        |t| find_closest_pair_inner(right, right_strip, t, leaf_size),
+    );
 
     // Get minimum distance
     let minimum = left_minimum.min(right_minimum);
 
     // Filter out all points not in the "strip", sort by y coordinate.
-    let strip = points
-        .iter()
-        .filter(|p| (p.x - median.x).abs() < minimum.distance().0)
-        .sorted_by(|a, b| a.y.partial_cmp(&b.y).unwrap());
+    let strip = closest::fill_strip(points, median, minimum.distance(), strip);
+    strip.sort_unstable_by(|a, b| a.y.partial_cmp(&b.y).unwrap());
 
     // Return the new minimum if the strip had a better value
     match closest::find_minimum_in_strip(strip, minimum.distance()) {
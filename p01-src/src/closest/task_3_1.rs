@@ -1,53 +1,86 @@
-use crate::closest::{find_minimum_bruteforce, find_minimum_in_strip};
+use crate::closest::{self, fill_strip, find_minimum_bruteforce, find_minimum_in_strip, join};
 use crate::geometry::{Point, PointPair};
-use itertools::Itertools;
 
 /// Task 3 (1): Divide and conquer, pre-sort points by x-coordinates
 #[derive(Clone, Debug)]
 pub struct Task3QuickSort {
     points: Vec<Point>,
+    threads: usize,
+    leaf_size: usize,
 }
 
 impl Task3QuickSort {
+    /// The leaf size used unless [`with_leaf_size`](Self::with_leaf_size) is called.
+    pub const DEFAULT_LEAF_SIZE: usize = 16;
+
     /// Create a new `Task3(1)` struct.
     pub fn new(points: Vec<Point>) -> Self {
-        Self { points }
+        Self {
+            points,
+            threads: 1,
+            leaf_size: Self::DEFAULT_LEAF_SIZE,
+        }
+    }
+
+    /// Solve the two halves of large divide steps concurrently, using up to `threads` threads.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Solve steps of at most `leaf_size` points by brute force. Values below
+    /// [`MIN_LEAF_SIZE`](crate::closest::MIN_LEAF_SIZE) are raised to it.
+    pub fn with_leaf_size(mut self, leaf_size: usize) -> Self {
+        self.leaf_size = leaf_size.max(closest::MIN_LEAF_SIZE);
+        self
     }
 
     /// Find the closest pair of points in the list.
     pub fn find_closest_pair(&mut self) -> PointPair {
         self.points
             .sort_unstable_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
-        find_closest_pair_inner(&mut self.points)
+        // The only allocation: every level builds its strip in its part of this buffer
+        let mut strip = self.points.clone();
+
+        find_closest_pair_inner(&mut self.points, &mut strip, self.threads, self.leaf_size)
     }
 }
 
-fn find_closest_pair_inner(points: &mut [Point]) -> PointPair {
+/// Finds the closest pair in `points`, which must be sorted by x-coordinate. `strip` is a buffer
+/// of the same length.
+fn find_closest_pair_inner(
+    points: &mut [Point],
+    strip: &mut [Point],
+    threads: usize,
+    leaf_size: usize,
+) -> PointPair {
     // Reference: W3 L3
 
     // Base case: we can't recurse any further
-    if points.len() <= 3 {
-        return find_minimum_bruteforce(points.iter());
+    if points.len() <= leaf_size {
+        return find_minimum_bruteforce(points);
     }
 
     // We have already sorted the entire array
     let length = points.len();
     let (left, right) = points.split_at_mut(length / 2);
+    let (left_strip, right_strip) = strip.split_at_mut(length / 2);
     let median = *right.first().unwrap();
 
     // Recursively solve the problem by left and right
-    let left_minimum = find_closest_pair_inner(left);
-
-    let right_minimum = find_closest_pair_inner(right);
+    let (left_minimum, right_minimum) = join(
+        threads,
+        length,
+        |t| find_closest_pair_inner(left, left_strip, t, leaf_size),
+        |t| find_closest_pair_inner(right, right_strip, t, leaf_size),
+    );
 
     // Get minimum distance
     let minimum = left_minimum.min(right_minimum);
 
     // Filter out all points not in the "strip", sort by y coordinate.
-    let strip = points
-        .iter()
-        .filter(|p| (p.x - median.x).abs() < minimum.distance().0)
-        .sorted_by(|a, b| a.y.partial_cmp(&b.y).unwrap());
+    let strip = fill_strip(points, median, minimum.distance(), strip);
+    strip.sort_unstable_by(|a, b| a.y.partial_cmp(&b.y).unwrap());
 
     match find_minimum_in_strip(strip, minimum.distance()) {
         Some(m) => m,
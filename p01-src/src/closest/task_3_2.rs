@@ -1,4 +1,4 @@
-use crate::closest::{find_minimum_bruteforce, find_minimum_in_strip};
+use crate::closest::{self, fill_strip, find_minimum_bruteforce, find_minimum_in_strip, join};
 use crate::geometry::{Point, PointPair};
 
 /// Task 3 (1): Divide and conquer, pre-sort points by x-coordinates, then sort by y-coordinates
@@ -6,12 +6,34 @@ use crate::geometry::{Point, PointPair};
 #[derive(Clone, Debug)]
 pub struct Task3SortedY {
     points: Vec<Point>,
+    threads: usize,
+    leaf_size: usize,
 }
 
 impl Task3SortedY {
+    /// The leaf size used unless [`with_leaf_size`](Self::with_leaf_size) is called.
+    pub const DEFAULT_LEAF_SIZE: usize = 16;
+
     /// Create a new `Task3(2)` struct.
     pub fn new(points: Vec<Point>) -> Self {
-        Self { points }
+        Self {
+            points,
+            threads: 1,
+            leaf_size: Self::DEFAULT_LEAF_SIZE,
+        }
+    }
+
+    /// Solve the two halves of large divide steps concurrently, using up to `threads` threads.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Solve steps of at most `leaf_size` points by brute force. Values below
+    /// [`MIN_LEAF_SIZE`](crate::closest::MIN_LEAF_SIZE) are raised to it.
+    pub fn with_leaf_size(mut self, leaf_size: usize) -> Self {
+        self.leaf_size = leaf_size.max(closest::MIN_LEAF_SIZE);
+        self
     }
 
     /// Find the closest pair of points in the list.
@@ -19,50 +41,94 @@ impl Task3SortedY {
         self.points
             .sort_unstable_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
 
-        find_closest_pair_inner(&mut self.points)
+        // The only allocation: every level merges into and filters into this buffer
+        let mut scratch = self.points.clone();
+
+        find_closest_pair_inner(
+            &mut self.points,
+            &mut scratch,
+            Output::Points,
+            self.threads,
+            self.leaf_size,
+        )
+    }
+}
+
+/// Which of the two buffers a recursive call leaves its points in, sorted by y-coordinate.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Output {
+    Points,
+    Scratch,
+}
+
+impl Output {
+    fn other(self) -> Self {
+        match self {
+            Output::Points => Output::Scratch,
+            Output::Scratch => Output::Points,
+        }
     }
 }
 
-fn find_closest_pair_inner(points: &mut [Point]) -> PointPair {
+/// Finds the closest pair in `points`, which must be sorted by x-coordinate.
+///
+/// `scratch` is a buffer of the same length. Each level asks its children to leave their halves
+/// sorted by y-coordinate in the opposite buffer to its own `output`, then merges them into
+/// `output`. This leaves the other buffer free to hold the strip, so no level needs to allocate or
+/// copy back.
+fn find_closest_pair_inner(
+    points: &mut [Point],
+    scratch: &mut [Point],
+    output: Output,
+    threads: usize,
+    leaf_size: usize,
+) -> PointPair {
     // Reference: W2 L3
+    debug_assert_eq!(points.len(), scratch.len());
 
     // Base case: we can't recurse any further
-    if points.len() <= 3 {
+    if points.len() <= leaf_size {
         points.sort_by(|a, b| a.y.partial_cmp(&b.y).unwrap());
-        return find_minimum_bruteforce(points.iter());
+        if output == Output::Scratch {
+            scratch.copy_from_slice(points);
+        }
+        return find_minimum_bruteforce(points);
     }
 
     // We have already sorted the entire array
     let length = points.len();
     let (left_x, right_x) = points.split_at_mut(length / 2);
+    let (left_scratch, right_scratch) = scratch.split_at_mut(length / 2);
     let median = *right_x.first().unwrap();
 
-    let left_x_len = left_x.len();
-
     // Recursively solve the problem by left and right
-    let left_minimum = find_closest_pair_inner(left_x);
-
-    let right_minimum = find_closest_pair_inner(right_x);
+    let (left_minimum, right_minimum) = join(
+        threads,
+        length,
+        |t| find_closest_pair_inner(left_x, left_scratch, output.other(), t, leaf_size),
+        |t| find_closest_pair_inner(right_x, right_scratch, output.other(), t, leaf_size),
+    );
 
     // Get minimum distance
     let minimum = left_minimum.min(right_minimum);
 
-    // Merge
-    let mut points_cpy = points.to_vec();
-    merge(
-        &points[0..left_x_len],
-        &points[left_x_len..],
-        &mut points_cpy,
-    );
-    points.copy_from_slice(&points_cpy); // memcpy
+    // Merge the halves into the output, which frees up the other buffer
+    let (sorted, free) = match output {
+        Output::Points => {
+            merge(&scratch[..length / 2], &scratch[length / 2..], points);
+            (&*points, scratch)
+        }
+        Output::Scratch => {
+            merge(&points[..length / 2], &points[length / 2..], scratch);
+            (&*scratch, points)
+        }
+    };
 
     // Check invariant. All points should be sorted by y-coordinates
-    debug_assert!((0..points.len() - 1).all(|i| points[i].y <= points[i + 1].y));
+    debug_assert!((0..sorted.len() - 1).all(|i| sorted[i].y <= sorted[i + 1].y));
 
-    // Filter to strip
-    let strip = points
-        .iter()
-        .filter(|p| (p.x - median.x).abs() < minimum.distance().0);
+    // Filter to strip, reusing the free buffer
+    let strip = fill_strip(sorted, median, minimum.distance(), free);
 
     match find_minimum_in_strip(strip, minimum.distance()) {
         Some(m) => m,
@@ -1,191 +1,287 @@
-use crate::geometry::{Distance, Point, PointPair};
-use fnv::FnvHashMap;
+use crate::closest::{map_chunks, map_chunks_mut, PARALLEL_CUTOFF};
+use crate::geometry::{Point, PointPair};
+use crate::spatial::{closest_in_neighbourhood, skip_self, Mesh, MeshPoint, PointsInNeighbour};
+use fnv::FnvHasher;
 use rand::prelude::*;
+use std::hash::{Hash, Hasher};
+use std::panic;
+use std::thread;
 
-/// A single mesh, indexed by its position along the `x` and `y` axis.
-#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
-pub struct MeshPoint {
-    xth: isize,
-    yth: isize,
-}
-
-/// A collection of meshes with a defined edge-to-edge size.
+/// A mesh split into shards by mesh index, so that each shard can be built on its own thread.
+///
+/// The points are first split by shard in one pass over chunks of them, and then each shard only
+/// adds the points in the meshes it owns, so building the shards needs no locking or merging
+/// afterwards.
 ///
-/// Internally, this is represented by a HashMap of x and y index to a vector of points. When an
-/// item is added into the collection, it is placed into the correct 'bucket' by finding out its
-/// closest index.
-pub struct Mesh {
-    size: f64,
-    mesh: FnvHashMap<MeshPoint, Vec<Point>>,
+/// The default sharded mesh has no shards, and must be filled with
+/// [`rebuild`](ShardedMesh::rebuild) before it is queried.
+#[derive(Default)]
+pub struct ShardedMesh {
+    shards: Vec<Mesh>,
 }
 
-impl Mesh {
-    /// Create a new mesh with a specified size.
+impl ShardedMesh {
+    /// Create a mesh of a specified size containing all of `points`, using up to `threads` shards.
+    /// Lists with fewer than `PARALLEL_CUTOFF` points are built as a single shard.
     /// # Panics
     /// Function will panic if the size is zero.
-    pub fn new(size: f64) -> Self {
-        debug_assert!(size > 0.0);
-        Self {
-            size,
-            mesh: FnvHashMap::default(),
-        }
+    pub fn new(size: f64, points: &[Point], threads: usize) -> Self {
+        let mut mesh = Self::default();
+        mesh.rebuild(size, points, threads);
+        mesh
     }
 
-    /// Add a point to the mesh, but do not check if the point has any neighbours.
-    pub fn add_point_unchecked(&mut self, point: Point) {
-        let point_mp = self.get_meshpoint_of_point(point);
+    /// Returns the points in a single mesh.
+    fn points_in_mesh(&self, point_mp: MeshPoint) -> &[Point] {
+        self.shards[shard_of(point_mp, self.shards.len())].points_in_mesh(point_mp)
+    }
 
-        let v = self.mesh.entry(point_mp).or_insert_with(Vec::new);
-        v.push(point);
+    /// Returns the meshes neighbouring the mesh the point belongs to, including its own.
+    fn neighbourhood(&self, point: Point) -> impl Iterator<Item = &[Point]> {
+        let mesh = &self.shards[0];
+        mesh.get_neighbours_of_mesh(mesh.get_meshpoint_of_point(point))
+            .map(move |m| self.points_in_mesh(m))
     }
 
-    /// Add a point to the mesh and returns whether the point added had any neighbours. This function
-    /// involves more lookups than the unchecked variant and requires more computation.
-    pub fn add_point(&mut self, point: Point) -> PointsInNeighbour {
-        let point_mp = self.get_meshpoint_of_point(point);
+    /// Replace the contents of the mesh with `points` in meshes of a new size, as if it had been
+    /// created by [`new`](ShardedMesh::new). The allocations of the shards are reused.
+    /// # Panics
+    /// Function will panic if the size is zero.
+    pub fn rebuild(&mut self, size: f64, points: &[Point], threads: usize) {
+        let count = if points.len() < PARALLEL_CUTOFF {
+            1
+        } else {
+            threads.max(1)
+        };
+        self.shards.resize_with(count, Mesh::default);
+        for mesh in &mut self.shards {
+            mesh.reset(size);
+        }
 
-        // If any of the neighbours contain a point
-        let has_neighbour = self.neighbour_is_populated(point_mp);
+        if count == 1 {
+            let mesh = &mut self.shards[0];
+            mesh.reserve(points.len());
+            for &point in points {
+                mesh.add_point_unchecked(point);
+            }
+            return;
+        }
 
-        let v = self.mesh.entry(point_mp).or_insert_with(Vec::new);
-        v.push(point);
+        // Each chunk of the points is split into one bucket per shard, so that every point is only
+        // hashed once, and then each shard is built from its bucket of every chunk
+        let mesh = &self.shards[0];
+        let buckets = map_chunks(threads, points, |_, chunk| {
+            let mut buckets = vec![Vec::new(); count];
+            for &point in chunk {
+                buckets[shard_of(mesh.get_meshpoint_of_point(point), count)].push(point);
+            }
+            buckets
+        });
 
-        has_neighbour
+        thread::scope(|s| {
+            let buckets = &buckets;
+            let handles = self
+                .shards
+                .iter_mut()
+                .enumerate()
+                .map(|(shard, mesh)| {
+                    s.spawn(move || {
+                        mesh.reserve(buckets.iter().map(|b| b[shard].len()).sum());
+                        for bucket in buckets {
+                            for &point in &bucket[shard] {
+                                mesh.add_point_unchecked(point);
+                            }
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            for h in handles {
+                h.join().unwrap_or_else(|e| panic::resume_unwind(e));
+            }
+        })
     }
 
-    /// Finds the closest pair to the point given in its neighbourhood.
+    /// Finds the closest pair to a point in the mesh among the points in its neighbourhood.
     /// # Returns
     /// Function returns `None` if it is the only point in its neighbourhood.
     pub fn closest_pair_to_point_in_neighbour(&self, point: Point) -> Option<PointPair> {
-        let point_mp = self.get_meshpoint_of_point(point);
-
-        let closest = self
-            .get_neighbours_of_mesh(point_mp)
-            .iter()
-            .flat_map(|p| self.mesh.get(p))
-            .flatten()
-            .filter(|p| **p != point)
-            .min_by(|a, b| {
-                a.distance_to(point)
-                    .0
-                    .partial_cmp(&b.distance_to(point).0)
-                    .unwrap()
-            });
-
-        closest.map(|p| PointPair(point, *p))
+        closest_in_neighbourhood(self.neighbourhood(point), point)
     }
 
-    /// Checks whether a neighbourhood is populated, given a single mesh.
-    fn neighbour_is_populated(&self, point_mp: MeshPoint) -> PointsInNeighbour {
-        if self
-            .get_neighbours_of_mesh(point_mp)
-            .iter()
-            .flat_map(|p| self.mesh.get(p))
-            .any(|hs| hs.len() > 1)
-        {
+    /// Returns whether any other point is in the neighbourhood of a point in the mesh.
+    pub fn has_neighbour(&self, point: Point) -> PointsInNeighbour {
+        if self.neighbourhood(point).map(|m| m.len()).sum::<usize>() > 1 {
             PointsInNeighbour::Yes
         } else {
             PointsInNeighbour::No
         }
     }
+}
+
+/// The filtering rounds of the sieve of [`Task4`], filtering a point out if no other point is in its
+/// neighbourhood once every point is in `mesh`.
+///
+/// Returns the closest of the pairs found each round between the random point and its nearest
+/// point among those left. A point is only filtered out when no point is within a third of that
+/// round's distance of it, and one of the closest pair is filtered out in some round, so the
+/// closest distance of `points` is more than a third of the distance returned, up to rounding, and
+/// at most the distance returned. A distance of zero is a duplicate, and so the closest pair.
+/// # Panics
+/// Function will panic if there are fewer than two points.
+fn sieve<R: Rng + ?Sized>(
+    points: &[Point],
+    mesh: &mut ShardedMesh,
+    threads: usize,
+    rng: &mut R,
+) -> PointPair {
+    let mut points_filtering = points.to_vec();
+    let mut best: Option<PointPair> = None;
+    loop {
+        // Pick a random point from collection
+        let index = rng.gen_range(0..points_filtering.len());
 
-    /// Returns the mesh a point belongs to.
-    fn get_meshpoint_of_point(&self, point: Point) -> MeshPoint {
-        MeshPoint {
-            xth: (point.x / self.size).floor() as isize,
-            yth: (point.y / self.size).floor() as isize,
+        // Compute closest distance to all points, find minimum
+        let closest = Task4::closest_pair_to_index(&points_filtering, index, threads);
+        let minimum = closest.distance();
+        if minimum.0 == 0.0 {
+            return closest;
         }
-    }
+        if best.is_none_or(|b| closest.distance() < b.distance()) {
+            best = Some(closest);
+        }
+
+        // Construct mesh with size minimum / 3, remove points that are alone in their
+        // neighbourhood
+        mesh.rebuild(minimum.0 / 3_f64, &points_filtering, threads);
+        retain_in_chunks(&mut points_filtering, threads, |p| {
+            match mesh.has_neighbour(*p) {
+                PointsInNeighbour::Yes => true,
+                PointsInNeighbour::No => false,
+            }
+        });
 
-    /// Returns the neighbours of a mesh.
-    fn get_neighbours_of_mesh(&self, meshpoint: MeshPoint) -> [MeshPoint; 9] {
-        let MeshPoint { xth: cx, yth: cy } = meshpoint;
-
-        [
-            MeshPoint {
-                xth: cx - 1,
-                yth: cy + 1,
-            },
-            MeshPoint {
-                xth: cx,
-                yth: cy + 1,
-            },
-            MeshPoint {
-                xth: cx + 1,
-                yth: cy + 1,
-            },
-            MeshPoint {
-                xth: cx - 1,
-                yth: cy,
-            },
-            MeshPoint { xth: cx, yth: cy },
-            MeshPoint {
-                xth: cx + 1,
-                yth: cy,
-            },
-            MeshPoint {
-                xth: cx - 1,
-                yth: cy - 1,
-            },
-            MeshPoint {
-                xth: cx,
-                yth: cy - 1,
-            },
-            MeshPoint {
-                xth: cx + 1,
-                yth: cy - 1,
-            },
-        ]
+        // Stop when empty
+        if points_filtering.len() < 2 {
+            return best.unwrap();
+        }
     }
 }
 
-/// Whether a point had any neighbour
-pub enum PointsInNeighbour {
-    /// Yes
-    Yes,
-    /// No (it was alone)
-    No,
+/// Returns the shard that owns a mesh.
+fn shard_of(point_mp: MeshPoint, shards: usize) -> usize {
+    let mut hasher = FnvHasher::default();
+    point_mp.hash(&mut hasher);
+    (hasher.finish() % shards as u64) as usize
 }
 
+/// Retains only the points matching `keep`, in place and in order, deciding on one chunk of the
+/// points per thread.
+fn retain_in_chunks(points: &mut Vec<Point>, threads: usize, keep: impl Fn(&Point) -> bool + Sync) {
+    // Each chunk moves the points it keeps to its front
+    let kept = map_chunks_mut(threads, points, |start, chunk| {
+        let mut kept = 0;
+        for i in 0..chunk.len() {
+            if keep(&chunk[i]) {
+                chunk[kept] = chunk[i];
+                kept += 1;
+            }
+        }
+        (start, kept)
+    });
+
+    // Then the kept points of every chunk are moved next to each other
+    let mut length = 0;
+    for (start, kept) in kept {
+        points.copy_within(start..start + kept, length);
+        length += kept;
+    }
+    points.truncate(length);
+}
+
+/// A fixed seed for [`Task4`] and the other seeded solvers, used by the tests and benchmarks so
+/// that every run makes the same random choices.
+pub const TASK_4_SEED: u64 = 0x4749_4232_3050_4C53;
+
 /// Task 3 (1): A randomised algorithm as devised by Khuller, Matias 2009.
 #[derive(Clone, Debug)]
 pub struct Task4 {
     points: Vec<Point>,
+    threads: usize,
+    seed: u64,
 }
 
 impl Task4 {
-    /// Create a new `Task4` struct.
+    /// Create a new `Task4` struct. The random number generator is seeded from the operating
+    /// system's entropy source, so that an input cannot be crafted against a known seed.
     pub fn new(points: Vec<Point>) -> Self {
-        Self { points }
+        Self {
+            points,
+            threads: 1,
+            seed: rand::random(),
+        }
+    }
+
+    /// Seed the random number generator with `seed`, so that runs can be reproduced.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The seed `find_closest_pair` uses.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Use the parallel variant of the sieve with up to `threads` threads, if `threads` is more
+    /// than one.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
     }
 
     /// Find the closest pair of points in the list.
     pub fn find_closest_pair(&self) -> PointPair {
+        self.find_closest_pair_with_rng(&mut StdRng::seed_from_u64(self.seed))
+    }
+
+    /// Find the closest pair of points in the list, picking random points with `rng` instead of
+    /// the seed.
+    pub fn find_closest_pair_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> PointPair {
+        if self.threads > 1 {
+            return self.find_closest_pair_parallel(rng);
+        }
+
         // A Simple Randomized Sieve Algorithm for the Closest-Pair Problem. (Khuller, Matias 2009)
-        let mut rng = StdRng::seed_from_u64(0x4749_4232_3050_4C53);
         let mut points_filtering = self.points.clone();
 
+        // Every round, and the final phase, reuses the allocations of this mesh
+        let mut mesh = Mesh::default();
+
         // Filtering
         let mut minimum;
         let mut random;
         loop {
             // Pick a random point from collection
-            random = *(points_filtering.choose(&mut rng).unwrap());
+            random = *(points_filtering.choose(rng).unwrap());
 
             // Compute closest distance to all points, find minimum
-            minimum = Task4::minimum_distance_to(&points_filtering, random);
+            let closest = Task4::closest_pair_to(&points_filtering, random);
+            minimum = closest.distance();
+
+            // Nothing can be closer than a duplicate, and a mesh of size zero cannot be built
+            if minimum.0 == 0.0 {
+                return closest;
+            }
 
             // Construct mesh with size minimum / 3
-            let mut mesh = Mesh::new(minimum.0 / 3_f64);
+            mesh.reset(minimum.0 / 3_f64);
+            mesh.reserve(points_filtering.len());
 
             // Add points to mesh, remove points that are alone in their neighbourhood
-            points_filtering = points_filtering
-                .into_iter()
-                .filter(|p| match mesh.add_point(*p) {
-                    PointsInNeighbour::Yes => true,
-                    PointsInNeighbour::No => false,
-                })
-                .collect();
+            points_filtering.retain(|p| match mesh.add_point(*p) {
+                PointsInNeighbour::Yes => true,
+                PointsInNeighbour::No => false,
+            });
 
             // Stop when empty
             if points_filtering.len() < 2 {
@@ -194,8 +290,15 @@ impl Task4 {
         }
 
         // Construct mesh of size minimum
-        minimum = Task4::minimum_distance_to(&self.points, random);
-        let mut mesh = Mesh::new(minimum.0);
+        let closest = Task4::closest_pair_to(&self.points, random);
+        minimum = closest.distance();
+        if minimum.0 == 0.0 {
+            return closest;
+        }
+        // Widen the mesh by a few ulps, as a pair whose distance rounds down to exactly `minimum`
+        // could otherwise be two meshes apart
+        mesh.reset(minimum.0 * (1.0 + 4.0 * f64::EPSILON));
+        mesh.reserve(self.points.len());
 
         for point in &self.points {
             mesh.add_point_unchecked(*point);
@@ -208,10 +311,84 @@ impl Task4 {
             .unwrap()
     }
 
-    fn minimum_distance_to(points: &[Point], point: Point) -> Distance {
-        let min_point = points
-            .iter()
-            .filter(|&&p| p != point)
+    /// Find the closest pair of points in the list with a [`ShardedMesh`], sharing the work between
+    /// threads.
+    ///
+    /// Every step of the sieve is the same as the sequential version, except that a point is
+    /// filtered out if no other point is in its neighbourhood once every point has been added to
+    /// the mesh. This does not depend on the order that points are added in, so the mesh can be
+    /// built all at once, and in parallel.
+    fn find_closest_pair_parallel<R: Rng + ?Sized>(&self, rng: &mut R) -> PointPair {
+        let threads = self.threads;
+        let mut mesh = ShardedMesh::default();
+
+        // Filtering
+        let sieved = sieve(&self.points, &mut mesh, threads, rng);
+        if sieved.distance().0 == 0.0 {
+            return sieved;
+        }
+
+        // Construct mesh of size minimum
+        let index = self.points.iter().position(|&p| p == sieved.0).unwrap();
+        let closest = Task4::closest_pair_to_index(&self.points, index, threads);
+        let minimum = closest.distance();
+        if minimum.0 == 0.0 {
+            return closest;
+        }
+        mesh.rebuild(
+            minimum.0 * (1.0 + 4.0 * f64::EPSILON),
+            &self.points,
+            threads,
+        );
+
+        map_chunks(threads, &self.points, |_, chunk| {
+            chunk
+                .iter()
+                .filter_map(|p| mesh.closest_pair_to_point_in_neighbour(*p))
+                .min_by(|a, b| a.distance().partial_cmp(&b.distance()).unwrap())
+        })
+        .into_iter()
+        .flatten()
+        .min_by(|a, b| a.distance().partial_cmp(&b.distance()).unwrap())
+        .unwrap()
+    }
+
+    /// Finds the closest pair between the point at `index` and any other point in the list,
+    /// sharing the work between threads.
+    pub(crate) fn closest_pair_to_index(
+        points: &[Point],
+        index: usize,
+        threads: usize,
+    ) -> PointPair {
+        let point = points[index];
+        map_chunks(threads, points, |start, chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| start + i != index)
+                .map(|(_, p)| *p)
+                .min_by(|a, b| {
+                    a.distance_to(point)
+                        .0
+                        .partial_cmp(&b.distance_to(point).0)
+                        .unwrap()
+                })
+        })
+        .into_iter()
+        .flatten()
+        .min_by(|a, b| {
+            a.distance_to(point)
+                .0
+                .partial_cmp(&b.distance_to(point).0)
+                .unwrap()
+        })
+        .map(|p| PointPair(point, p))
+        .unwrap()
+    }
+
+    /// Finds the closest pair between `point` and any other point in the list.
+    fn closest_pair_to(points: &[Point], point: Point) -> PointPair {
+        let min_point = skip_self(points.iter(), point)
             .min_by(|a, b| {
                 a.distance_to(point)
                     .0
@@ -219,6 +396,6 @@ impl Task4 {
                     .unwrap()
             })
             .unwrap();
-        min_point.distance_to(point)
+        PointPair(point, *min_point)
     }
 }
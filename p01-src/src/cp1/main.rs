@@ -1,10 +1,14 @@
 use closest_pairs::bail_error;
+use closest_pairs::cli::threads_or_exit;
 use closest_pairs::closest::task_1::Task1;
 use closest_pairs::parse::parse_stdin;
 use std::io;
 use std::io::Read;
 
 fn main() {
+    // Optional: --threads N solves large halves concurrently (0 uses every CPU)
+    let threads = threads_or_exit(std::env::args().skip(1));
+
     let mut buffer = String::new();
 
     // Read from stdin
@@ -15,7 +19,7 @@ fn main() {
     // Parse and create struct
     let mut points = {
         let (_, points) = parse_stdin(&buffer).unwrap_or_else(|_| bail_error!(1));
-        Task1::new(points)
+        Task1::new(points).with_threads(threads)
     };
 
     // Calculate
@@ -1,10 +1,26 @@
 use closest_pairs::bail_error;
+use closest_pairs::cli::{parse_u64, threads, ArgError, Args};
 use closest_pairs::closest::task_4::Task4;
 use closest_pairs::parse::parse_stdin;
 use std::io;
 use std::io::Read;
+use std::process;
+
+fn usage_error(e: ArgError) -> ! {
+    eprintln!("error: {}", e);
+    process::exit(2)
+}
 
 fn main() {
+    // Optional: --threads N uses the parallel sieve (0 uses every CPU), --seed SEED fixes the
+    // random points picked, and --verbose prints the seed used to stderr
+    let args = Args::parse(std::env::args().skip(1), &["threads", "seed"], &["verbose"])
+        .unwrap_or_else(|e| usage_error(e));
+    let threads = threads(&args).unwrap_or_else(|e| usage_error(e));
+    let seed = args
+        .value_with("seed", parse_u64)
+        .unwrap_or_else(|e| usage_error(e));
+
     let mut buffer = String::new();
 
     // Read from stdin
@@ -15,9 +31,17 @@ fn main() {
     // Parse and create struct
     let points = {
         let (_, points) = parse_stdin(&buffer).unwrap_or_else(|_| bail_error!(1));
-        Task4::new(points)
+        let task = Task4::new(points).with_threads(threads);
+        match seed {
+            Some(seed) => task.with_seed(seed),
+            None => task,
+        }
     };
 
+    if args.switch("verbose") {
+        eprintln!("seed: {:#x}", points.seed());
+    }
+
     // Calculate
     let closest = points.find_closest_pair();
 
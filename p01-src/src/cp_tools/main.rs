@@ -0,0 +1,375 @@
+use closest_pairs::bail_error;
+use closest_pairs::check::{check_case, find_cases, Runner};
+use closest_pairs::cli::{parse_u64, threads, Args};
+use closest_pairs::closest::approximate::Approximate;
+use closest_pairs::closest::auto::Profile;
+use closest_pairs::closest::{Algorithm, Options};
+use closest_pairs::dynamic::SlidingWindow;
+use closest_pairs::geometry::Distance;
+use closest_pairs::parse::{parse_stdin, parse_stream_line};
+use closest_pairs::verify::{verify, DEFAULT_REFERENCE_THRESHOLD};
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Instant;
+
+const USAGE: &str = "\
+Usage: cp_tools <command> [options]
+
+Commands:
+  solve [--algorithm NAME | --epsilon E] [--threads N] [--leaf-size N] [--seed SEED]
+        [--verbose] [FILE]
+      Find the closest pair distance of FILE (default: stdin) and print it as the spec
+      binaries do. NAME is one of task_1, task_3_sort_first, task_3_maintain_y, task_4,
+      kd_tree, randomized_incremental, plane_sweep or auto (default: task_3_maintain_y).
+      auto samples the input and picks a solver; --verbose prints which to stderr. With
+      --epsilon E, an approximate solver prints a distance at most 1 + E times the closest
+      instead. With --threads N, large divide-and-conquer steps are solved on up to
+      N threads (0 uses every CPU); the answer does not depend on N. With --leaf-size N,
+      divide-and-conquer steps of at most N points are solved by brute force (default: tuned
+      for each solver; at least 3). With --seed SEED, task_4 and randomized_incremental
+      make the same random choices on every run (default: a seed from the OS); --verbose
+      prints the seed used to stderr.
+
+  verify [--threshold N] [--seed SEED] [--verbose] PATH...
+      Run every solver on each input file (or every .in file in each directory) and compare
+      their answers. Inputs with at most N points (default: 5000) are also checked against
+      a brute-force reference. Exits with status 1 if any solver disagrees. --seed and
+      --verbose work as for solve.
+
+  check [--algorithm NAME | --binary PATH] [--seed SEED] [--verbose] DIR...
+      Run every name.in/name.out test case under each directory and compare the output.
+      By default every solver is run; use --algorithm to pick one solver or --binary to
+      run an executable such as target/release/cp1. Exits with status 1 if any case fails.
+      --seed and --verbose work as for solve, for the solvers run in this process.
+
+  stream [--window N] [--seconds T] [--every N]
+      Read points from stdin one per line, as `x y` or `x y time`, and print the closest pair
+      distance among the last N points, or the points of the last T seconds, after each point
+      (or after every N points with --every, and once more at the end). Without a time, a
+      point is timed by when it was read. The distance is printed as `inf` while there are
+      fewer than two points. A first line holding only the number of points is skipped, so
+      spec inputs can be streamed too.";
+
+/// Exit status when a check fails.
+const EXIT_FAILURE: i32 = 1;
+/// Exit status when the arguments are invalid.
+const EXIT_USAGE: i32 = 2;
+
+/// Prints an error and the usage, then quits the process.
+fn usage_error(message: impl std::fmt::Display) -> ! {
+    eprintln!("error: {}\n\n{}", message, USAGE);
+    process::exit(EXIT_USAGE)
+}
+
+/// Returns the `--seed` value, or draws a seed if none was given so that it can be printed.
+fn seed(args: &Args) -> u64 {
+    args.value_with("seed", parse_u64)
+        .unwrap_or_else(|e| usage_error(e))
+        .unwrap_or_else(rand::random)
+}
+
+/// Expands every directory in `paths` into the `.in` files it contains, sorted by name.
+fn input_files(paths: &[String]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for path in paths.iter().map(Path::new) {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)
+                .unwrap_or_else(|e| usage_error(format!("{}: {}", path.display(), e)))
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "in"))
+                .collect::<Vec<_>>();
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    files
+}
+
+fn solve_command(args: impl Iterator<Item = String>) {
+    let args = Args::parse(
+        args,
+        &["algorithm", "epsilon", "threads", "leaf-size", "seed"],
+        &["verbose"],
+    )
+    .unwrap_or_else(|e| usage_error(e));
+    let algorithm = args
+        .value::<Algorithm>("algorithm")
+        .unwrap_or_else(|e| usage_error(e));
+    let epsilon = args
+        .value_with("epsilon", |s| {
+            s.parse().ok().filter(|&e: &f64| e >= 0.0 && e.is_finite())
+        })
+        .unwrap_or_else(|e| usage_error(e));
+    if algorithm.is_some() && epsilon.is_some() {
+        usage_error("--algorithm and --epsilon cannot be used together");
+    }
+    let algorithm = algorithm.unwrap_or(Algorithm::Task3SortedY);
+    // Drawn here rather than by the solver, so that it can be printed
+    let seed = seed(&args);
+    let options = Options {
+        threads: threads(&args).unwrap_or_else(|e| usage_error(e)),
+        leaf_size: args
+            .value::<usize>("leaf-size")
+            .unwrap_or_else(|e| usage_error(e)),
+        seed: Some(seed),
+    };
+    let buffer = match args.positional() {
+        [] => {
+            let mut buffer = String::new();
+            io::stdin()
+                .read_to_string(&mut buffer)
+                .unwrap_or_else(|_| bail_error!(1));
+            buffer
+        }
+        [file] => fs::read_to_string(file).unwrap_or_else(|_| bail_error!(1)),
+        _ => usage_error("expected at most one input file"),
+    };
+
+    let (_, points) = parse_stdin(&buffer).unwrap_or_else(|_| bail_error!(1));
+
+    let verbose = args.switch("verbose");
+    let algorithm = match algorithm {
+        Algorithm::Auto => {
+            let profile = Profile::of(&points);
+            let chosen = profile.choose();
+            if verbose {
+                eprintln!("algorithm: {} ({})", chosen.name(), profile);
+            }
+            chosen
+        }
+        algorithm => algorithm,
+    };
+    if verbose
+        && matches!(
+            algorithm,
+            Algorithm::Task4 | Algorithm::RandomizedIncremental
+        )
+    {
+        eprintln!("seed: {:#x}", seed);
+    }
+
+    let closest = match epsilon {
+        Some(epsilon) => Approximate::new(points, epsilon).find_closest_pair(),
+        None => algorithm.find_closest_pair_with(points, &options),
+    };
+
+    unsafe {
+        closest.distance().pretty_print();
+    }
+}
+
+fn verify_command(args: impl Iterator<Item = String>) {
+    let args =
+        Args::parse(args, &["threshold", "seed"], &["verbose"]).unwrap_or_else(|e| usage_error(e));
+    let threshold = args
+        .value_with("threshold", |s| parse_u64(s).map(|t| t as usize))
+        .unwrap_or_else(|e| usage_error(e))
+        .unwrap_or(DEFAULT_REFERENCE_THRESHOLD);
+    let verbose = args.switch("verbose");
+    let seed = seed(&args);
+    if verbose {
+        eprintln!("seed: {:#x}", seed);
+    }
+    let options = Options {
+        seed: Some(seed),
+        ..Options::default()
+    };
+
+    if args.positional().is_empty() {
+        usage_error("no inputs given");
+    }
+
+    let mut failed = false;
+
+    for file in input_files(args.positional()) {
+        let buffer = match fs::read_to_string(&file) {
+            Ok(b) => b,
+            Err(e) => {
+                println!("FAIL {}: {}", file.display(), e);
+                failed = true;
+                continue;
+            }
+        };
+
+        let points = match parse_stdin(&buffer) {
+            Ok((_, points)) => points,
+            Err(_) => {
+                println!("skip {}: Incorrectly formatted input", file.display());
+                continue;
+            }
+        };
+
+        let report = verify(&points, threshold, &options);
+
+        if report.is_ok() {
+            println!(
+                "ok   {}: {} points, distance {}{}",
+                file.display(),
+                points.len(),
+                report.expected.map_or(f64::NAN, |p| p.distance().0),
+                if report.checked_against_reference {
+                    " (checked against brute force)"
+                } else {
+                    ""
+                }
+            );
+        } else {
+            println!("FAIL {}: {} points", file.display(), points.len());
+            failed = true;
+        }
+
+        for d in &report.disagreements {
+            if verbose || d.is_error() {
+                println!("     {}", d);
+            }
+        }
+        if verbose {
+            for (solver, pair) in &report.results {
+                println!("     {}: {:?}", solver, pair);
+            }
+        }
+    }
+
+    if failed {
+        process::exit(EXIT_FAILURE)
+    }
+}
+
+fn check_command(args: impl Iterator<Item = String>) {
+    let args = Args::parse(args, &["algorithm", "binary", "seed"], &["verbose"])
+        .unwrap_or_else(|e| usage_error(e));
+    let verbose = args.switch("verbose");
+
+    let algorithm = args
+        .value::<Algorithm>("algorithm")
+        .unwrap_or_else(|e| usage_error(e));
+    let binary = args
+        .value::<PathBuf>("binary")
+        .unwrap_or_else(|e| usage_error(e));
+
+    let seed = seed(&args);
+    if verbose && binary.is_none() {
+        eprintln!("seed: {:#x}", seed);
+    }
+    let options = Options {
+        seed: Some(seed),
+        ..Options::default()
+    };
+    let runners = match (algorithm, binary) {
+        (Some(_), Some(_)) => usage_error("--algorithm and --binary cannot be used together"),
+        (Some(algorithm), None) => vec![Runner::Solver(algorithm, options)],
+        (None, Some(binary)) => vec![Runner::Binary(binary, Vec::new())],
+        (None, None) => Algorithm::ALL
+            .iter()
+            .map(|&a| Runner::Solver(a, options))
+            .collect(),
+    };
+
+    if args.positional().is_empty() {
+        usage_error("no directories given");
+    }
+
+    let mut cases = Vec::new();
+    for dir in args.positional() {
+        cases.extend(
+            find_cases(Path::new(dir)).unwrap_or_else(|e| usage_error(format!("{}: {}", dir, e))),
+        );
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+
+    for runner in &runners {
+        for case in &cases {
+            match check_case(case, runner) {
+                Ok(outcome) if outcome.passed() => {
+                    passed += 1;
+                    if verbose {
+                        println!("pass {} [{}]", case.name(), runner.name());
+                    }
+                }
+                Ok(outcome) => {
+                    failed += 1;
+                    println!("FAIL {} [{}]", case.name(), runner.name());
+                    print!("{}", outcome.diff());
+                }
+                Err(e) => {
+                    failed += 1;
+                    println!("FAIL {} [{}]: {}", case.name(), runner.name(), e);
+                }
+            }
+        }
+    }
+
+    println!("{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        process::exit(EXIT_FAILURE)
+    }
+}
+
+fn stream_command(args: impl Iterator<Item = String>) {
+    let args =
+        Args::parse(args, &["window", "seconds", "every"], &[]).unwrap_or_else(|e| usage_error(e));
+    let max_points = args
+        .value_with("window", |s| s.parse().ok().filter(|&n: &usize| n > 0))
+        .unwrap_or_else(|e| usage_error(e));
+    let max_age = args
+        .value_with("seconds", |s| s.parse().ok().filter(|&t: &f64| t > 0.0))
+        .unwrap_or_else(|e| usage_error(e));
+    let every = args
+        .value_with("every", |s| s.parse().ok().filter(|&n: &usize| n > 0))
+        .unwrap_or_else(|e| usage_error(e))
+        .unwrap_or(1);
+    if !args.positional().is_empty() {
+        usage_error("stream reads from stdin only");
+    }
+
+    let start = Instant::now();
+    let mut window = SlidingWindow::new(max_points, max_age);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut print = |window: &SlidingWindow| {
+        let distance = window
+            .closest()
+            .map_or(Distance(f64::INFINITY), |p| p.distance());
+        writeln!(out, "{}", distance.to_spec_string())
+            .and_then(|_| out.flush())
+            .unwrap_or_else(|_| process::exit(EXIT_FAILURE));
+    };
+
+    let mut pushed = 0;
+    for (i, line) in io::stdin().lock().lines().enumerate() {
+        let line = line.unwrap_or_else(|_| bail_error!(1));
+        if line.trim().is_empty() || (i == 0 && line.trim().parse::<u64>().is_ok()) {
+            continue;
+        }
+        let (_, (point, time)) = parse_stream_line(&line).unwrap_or_else(|_| bail_error!(1));
+
+        window.push(point, time.unwrap_or_else(|| start.elapsed().as_secs_f64()));
+        pushed += 1;
+        if pushed % every == 0 {
+            print(&window);
+        }
+    }
+    if pushed % every != 0 {
+        print(&window);
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+
+    match args.next().as_deref() {
+        Some("solve") => solve_command(args),
+        Some("verify") => verify_command(args),
+        Some("check") => check_command(args),
+        Some("stream") => stream_command(args),
+        Some("help") | Some("--help") => println!("{}", USAGE),
+        Some(command) => usage_error(format!("unknown command `{}`", command)),
+        None => usage_error("no command given"),
+    }
+}
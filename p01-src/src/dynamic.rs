@@ -0,0 +1,683 @@
+use crate::geometry::{Point, PointPair};
+use crate::kdtree::KdTree;
+use fnv::{FnvHashMap, FnvHashSet};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+
+/// Identifies a point inserted into a [`DynamicClosestPair`]. Handles are never reused, so a handle
+/// to a removed point stays invalid.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Handle(u64);
+
+/// The coordinates of a point as bits, so that copies of a point have the same key. `-0.0` is keyed
+/// as `0.0`, as they are the same position.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Key(u64, u64);
+
+impl Key {
+    fn of(point: Point) -> Self {
+        Key((point.x + 0.0).to_bits(), (point.y + 0.0).to_bits())
+    }
+}
+
+/// The handles of every copy of a point, which share one site.
+#[derive(Clone, Debug)]
+struct Copies {
+    /// The position of the copies, as the first of them was inserted.
+    point: Point,
+    handles: Vec<Handle>,
+    /// The index of the key in `duplicated`, if there is more than one handle.
+    duplicated: usize,
+}
+
+/// Static [`KdTree`]s over the sites of a [`DynamicClosestPair`], at most one of each size class,
+/// and a short list of the sites added since the last merge.
+///
+/// The tree at index `i` holds at most `RECENT << i` points. Once the list is full, it is merged
+/// with the trees before the first free index into one tree there, as in a binary counter, so each
+/// point is built into `O(log n)` trees over its life. Removed sites are left in their trees and
+/// skipped by searches until a merge drops them, or they outnumber the rest and every tree is
+/// rebuilt as one.
+#[derive(Default)]
+struct Forest {
+    trees: Vec<Option<KdTree>>,
+    recent: Vec<Point>,
+    /// The sites removed but still in a tree.
+    removed: FnvHashSet<Key>,
+    /// The number of points in the trees and the list, counting removed sites.
+    len: usize,
+}
+
+impl Forest {
+    /// The number of sites added since the last merge that are searched one by one.
+    const RECENT: usize = 32;
+
+    /// Adds a site, which must not be in the forest already.
+    fn insert(&mut self, point: Point) {
+        // A site removed and added again is still in its tree
+        if self.removed.remove(&Key::of(point)) {
+            return;
+        }
+        self.len += 1;
+        self.recent.push(point);
+        if self.recent.len() < Self::RECENT {
+            return;
+        }
+
+        let mut merged = std::mem::take(&mut self.recent);
+        let mut index = 0;
+        while let Some(tree) = self.trees.get_mut(index).and_then(Option::take) {
+            merged.extend_from_slice(tree.points());
+            index += 1;
+        }
+        if index == self.trees.len() {
+            self.trees.push(None);
+        }
+        self.place(index, merged);
+    }
+
+    /// Removes a site, which must be in the forest.
+    fn remove(&mut self, point: Point) {
+        if let Some(index) = self
+            .recent
+            .iter()
+            .position(|&p| Key::of(p) == Key::of(point))
+        {
+            self.recent.swap_remove(index);
+            self.len -= 1;
+            return;
+        }
+        self.removed.insert(Key::of(point));
+        if 2 * self.removed.len() > self.len + Self::RECENT {
+            let mut points = std::mem::take(&mut self.recent);
+            for tree in self.trees.drain(..).flatten() {
+                points.extend_from_slice(tree.points());
+            }
+            let index = (points.len() / Self::RECENT + 1)
+                .next_power_of_two()
+                .trailing_zeros() as usize;
+            self.trees.resize_with(index + 1, || None);
+            self.place(index, points);
+        }
+    }
+
+    /// Builds a tree at `index` over `points`, dropping the removed sites.
+    fn place(&mut self, index: usize, mut points: Vec<Point>) {
+        let removed = &mut self.removed;
+        self.len -= points.len();
+        points.retain(|p| !removed.remove(&Key::of(*p)));
+        self.len += points.len();
+        self.trees[index] = Some(KdTree::new(points));
+    }
+
+    /// Finds the site closest to `point` other than `point` itself, and the number of sites looked
+    /// at.
+    fn nearest(&self, point: Point) -> (Option<Point>, usize) {
+        let key = Key::of(point);
+        let keep = |p: Point| {
+            let k = Key::of(p);
+            k != key && !self.removed.contains(&k)
+        };
+
+        let mut nearest = None;
+        let mut within = f64::INFINITY;
+        for &p in &self.recent {
+            let distance = p.distance_to(point).0;
+            if distance < within && keep(p) {
+                within = distance;
+                nearest = Some(p);
+            }
+        }
+
+        // The largest trees most likely hold the nearest site, which prunes the rest
+        let mut scanned = self.recent.len();
+        for tree in self.trees.iter().rev().flatten() {
+            let (found, looked_at) = tree.nearest_kept(point, within, keep);
+            scanned += looked_at;
+            if let Some(found) = found {
+                within = found.distance_to(point).0;
+                nearest = Some(found);
+            }
+        }
+        (nearest, scanned)
+    }
+}
+
+/// A point and the nearest other point to it when it was last searched for. The heap is ordered by
+/// distance, closest first.
+#[derive(Copy, Clone, Debug)]
+struct Candidate {
+    distance: f64,
+    from: Key,
+    to: Key,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance)
+    }
+}
+
+/// The closest pair of a set of points that changes over time.
+///
+/// Copies of a point share one site: the set keeps the handles of each position, and only the
+/// site searches or is searched for. While any position has two or more handles, the closest pair
+/// is two of them, at a distance of zero. Inserting or removing a copy of a position that is
+/// already in the set therefore takes `O(1)` time, however many copies there are.
+///
+/// Every site keeps a candidate pair with its nearest neighbour as of when it was inserted, in a
+/// heap ordered by distance. A candidate only goes stale when one of its sites is removed, at which
+/// point every site that had the removed site as its neighbour searches again. The closest pair of
+/// distinct points is always the closest candidate: of the two sites in it, the one inserted last
+/// found the other, or something closer that has since been removed and so searched again.
+///
+/// Nearest neighbours are found in `O(log n)` static [`KdTree`]s of the sites, of doubling sizes,
+/// which are merged as sites are inserted and rebuilt once removed sites outnumber the rest. The
+/// merges take `O(log² n)` amortised time per update, and the heap `O(log n)`. A k-d tree splits
+/// at medians rather than at a fixed size, so a dense cluster among sparse points is split as
+/// finely as it needs, and a search looks at about `O(log n)` points of each tree for most inputs.
+/// Points can still be placed so that a search looks at `O(√n)` points, and a removal searches
+/// again for every site that had found the removed site, of which there are at most a few for
+/// points that were not inserted in an adversarial order.
+#[derive(Default)]
+pub struct DynamicClosestPair {
+    /// The points of the sites, and of removed sites not yet dropped.
+    forest: Forest,
+    /// The point of each handle, and its index in the handles of its copies.
+    points: FnvHashMap<Handle, (Point, usize)>,
+    sites: FnvHashMap<Key, Copies>,
+    /// The sites with more than one handle.
+    duplicated: Vec<Key>,
+    /// The neighbour each site last found, if any.
+    neighbour: FnvHashMap<Key, Key>,
+    /// The sites whose neighbour is each site, the reverse of `neighbour`. Sites with none are left
+    /// out.
+    found_by: FnvHashMap<Key, Vec<Key>>,
+    candidates: BinaryHeap<Candidate>,
+    next: u64,
+    /// The number of points the searches have looked at, so that tests can bound the work.
+    #[cfg(test)]
+    scanned: usize,
+}
+
+impl DynamicClosestPair {
+    /// Create an empty set of points.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of points in the set.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns whether the set has no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Returns the point a handle refers to, or `None` if it has been removed.
+    pub fn get(&self, handle: Handle) -> Option<Point> {
+        self.points.get(&handle).map(|&(point, _)| point)
+    }
+
+    /// Add a point to the set, returning a handle that can be used to remove it.
+    pub fn insert(&mut self, point: Point) -> Handle {
+        let handle = Handle(self.next);
+        self.next += 1;
+
+        let key = Key::of(point);
+        if let Some(copies) = self.sites.get_mut(&key) {
+            // A copy of a site only joins its handles
+            self.points.insert(handle, (point, copies.handles.len()));
+            copies.handles.push(handle);
+            if copies.handles.len() == 2 {
+                copies.duplicated = self.duplicated.len();
+                self.duplicated.push(key);
+            }
+            return handle;
+        }
+
+        self.search(key, point);
+        self.points.insert(handle, (point, 0));
+        self.sites.insert(
+            key,
+            Copies {
+                point,
+                handles: vec![handle],
+                duplicated: 0,
+            },
+        );
+        self.forest.insert(point);
+        handle
+    }
+
+    /// Remove a point from the set.
+    /// # Returns
+    /// Function returns the point removed, or `None` if it had already been removed.
+    pub fn remove(&mut self, handle: Handle) -> Option<Point> {
+        let (point, index) = self.points.remove(&handle)?;
+        let key = Key::of(point);
+        let copies = self.sites.get_mut(&key).unwrap();
+        copies.handles.swap_remove(index);
+        if let Some(&moved) = copies.handles.get(index) {
+            self.points.get_mut(&moved).unwrap().1 = index;
+        }
+        match copies.handles.len() {
+            0 => {}
+            1 => {
+                let position = copies.duplicated;
+                self.duplicated.swap_remove(position);
+                if let Some(&moved) = self.duplicated.get(position) {
+                    self.sites.get_mut(&moved).unwrap().duplicated = position;
+                }
+                return Some(point);
+            }
+            _ => return Some(point),
+        }
+
+        // The last copy takes its site with it
+        let site = self.sites.remove(&key).unwrap().point;
+        self.forest.remove(site);
+        self.forget_neighbour(key);
+
+        // Every site whose neighbour was this site searches again
+        for other in self.found_by.remove(&key).unwrap_or_default() {
+            self.neighbour.remove(&other);
+            self.search(other, self.sites[&other].point);
+        }
+        self.discard_stale();
+        Some(point)
+    }
+
+    /// Returns the closest pair of points in the set, or `None` if there are fewer than two.
+    pub fn closest(&self) -> Option<PointPair> {
+        self.closest_handles()
+            .map(|(a, b)| PointPair(self.points[&a].0, self.points[&b].0))
+    }
+
+    /// Returns the handles of the closest pair of points in the set, or `None` if there are fewer
+    /// than two.
+    pub fn closest_handles(&self) -> Option<(Handle, Handle)> {
+        if let Some(key) = self.duplicated.last() {
+            let handles = &self.sites[key].handles;
+            return Some((handles[0], handles[1]));
+        }
+        self.candidates
+            .peek()
+            .map(|c| (self.sites[&c.from].handles[0], self.sites[&c.to].handles[0]))
+    }
+
+    /// Finds the nearest other site to a site, which must have no neighbour recorded, and records
+    /// it as a candidate.
+    fn search(&mut self, key: Key, point: Point) {
+        let (nearest, _scanned) = self.forest.nearest(point);
+        #[cfg(test)]
+        {
+            self.scanned += _scanned;
+        }
+        if let Some(nearest) = nearest {
+            let nearest_key = Key::of(nearest);
+            self.neighbour.insert(key, nearest_key);
+            self.found_by.entry(nearest_key).or_default().push(key);
+            self.candidates.push(Candidate {
+                distance: point.distance_to(nearest).0,
+                from: key,
+                to: nearest_key,
+            });
+        }
+    }
+
+    /// Removes the neighbour recorded for a site, and the site from those that found it.
+    fn forget_neighbour(&mut self, key: Key) {
+        let nearest = match self.neighbour.remove(&key) {
+            Some(nearest) => nearest,
+            None => return,
+        };
+        if let Some(found_by) = self.found_by.get_mut(&nearest) {
+            found_by.retain(|&k| k != key);
+            if found_by.is_empty() {
+                self.found_by.remove(&nearest);
+            }
+        }
+    }
+
+    /// Returns whether a candidate is still the neighbour of its site.
+    fn is_current(&self, candidate: &Candidate) -> bool {
+        self.neighbour.get(&candidate.from) == Some(&candidate.to)
+    }
+
+    /// Pops candidates that are no longer current off the top of the heap, and drops every stale
+    /// candidate once they outnumber the sites.
+    fn discard_stale(&mut self) {
+        while let Some(top) = self.candidates.peek() {
+            if self.is_current(top) {
+                break;
+            }
+            self.candidates.pop();
+        }
+
+        if self.candidates.len() > 2 * self.sites.len() + 16 {
+            let candidates = std::mem::take(&mut self.candidates);
+            self.candidates = candidates
+                .into_iter()
+                .filter(|c| self.is_current(c))
+                .collect();
+        }
+    }
+}
+
+/// The closest pair of the most recent points of a stream.
+///
+/// Points are pushed with the time they arrived, in seconds. The window keeps at most `max_points`
+/// points, counting the newest, and drops points at least `max_age` seconds older than the newest.
+/// Either limit may be left out. Points leave in the order they were pushed, so times should not
+/// decrease. Repeated readings of the same position are copies in the [`DynamicClosestPair`], so
+/// pushing one takes `O(1)` time however many of them are in the window.
+pub struct SlidingWindow {
+    set: DynamicClosestPair,
+    /// The points in the window and their times, oldest first.
+    window: VecDeque<(Handle, f64)>,
+    max_points: Option<usize>,
+    max_age: Option<f64>,
+}
+
+impl SlidingWindow {
+    /// Create an empty window holding at most `max_points` points of at most `max_age` seconds.
+    /// # Panics
+    /// Panics if `max_points` is zero.
+    pub fn new(max_points: Option<usize>, max_age: Option<f64>) -> Self {
+        assert_ne!(max_points, Some(0), "a window must hold at least one point");
+        SlidingWindow {
+            set: DynamicClosestPair::new(),
+            window: VecDeque::new(),
+            max_points,
+            max_age,
+        }
+    }
+
+    /// The number of points in the window.
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Returns whether the window has no points.
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    /// Add a point that arrived at `time`, dropping the points that fall out of the window.
+    pub fn push(&mut self, point: Point, time: f64) {
+        while let Some(&(handle, oldest)) = self.window.front() {
+            let full = self.max_points.is_some_and(|n| self.window.len() >= n);
+            let expired = self.max_age.is_some_and(|age| time - oldest >= age);
+            if !full && !expired {
+                break;
+            }
+            self.set.remove(handle);
+            self.window.pop_front();
+        }
+
+        self.window.push_back((self.set.insert(point), time));
+    }
+
+    /// Returns the closest pair of points in the window, or `None` if there are fewer than two.
+    pub fn closest(&self) -> Option<PointPair> {
+        self.set.closest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::closest::{find_minimum_bruteforce, Algorithm};
+    use crate::generators;
+    use rand::prelude::*;
+
+    /// Checks the closest pair against brute force over the points in the set.
+    fn check(set: &DynamicClosestPair, points: &FnvHashMap<Handle, Point>) {
+        assert_eq!(set.len(), points.len());
+
+        // Every site with a neighbour is listed once, under that neighbour
+        assert_eq!(
+            set.found_by.values().map(Vec::len).sum::<usize>(),
+            set.neighbour.len()
+        );
+        for (nearest, found_by) in &set.found_by {
+            assert!(!found_by.is_empty());
+            assert!(found_by.iter().all(|k| set.neighbour[k] == *nearest));
+        }
+
+        // Every site is in the forest once, and every handle is listed once, under the site of its
+        // point, and every site with copies is listed as duplicated
+        let mut in_forest = FnvHashMap::default();
+        let trees = set.forest.trees.iter().flatten();
+        for &p in trees.flat_map(|t| t.points()).chain(&set.forest.recent) {
+            *in_forest.entry(Key::of(p)).or_insert(0) += 1;
+        }
+        assert_eq!(in_forest.values().sum::<usize>(), set.forest.len);
+        assert_eq!(in_forest.len(), set.forest.len);
+        assert_eq!(in_forest.len(), set.sites.len() + set.forest.removed.len());
+        assert!(set.sites.keys().all(|k| in_forest.contains_key(k)));
+        assert!(set.forest.removed.iter().all(|k| in_forest.contains_key(k)));
+        for (key, copies) in &set.sites {
+            assert!(!copies.handles.is_empty());
+            for (i, h) in copies.handles.iter().enumerate() {
+                assert_eq!(set.points[h], (points[h], i));
+                assert_eq!(Key::of(points[h]), *key);
+            }
+            if copies.handles.len() > 1 {
+                assert_eq!(set.duplicated[copies.duplicated], *key);
+            }
+        }
+        assert_eq!(
+            set.duplicated.len(),
+            set.sites.values().filter(|c| c.handles.len() > 1).count()
+        );
+
+        if points.len() < 2 {
+            assert!(set.closest().is_none());
+            return;
+        }
+        let expected = find_minimum_bruteforce(&points.values().copied().collect::<Vec<_>>());
+        let (a, b) = set.closest_handles().unwrap();
+        assert_eq!(
+            (set.get(a), set.get(b)),
+            (points.get(&a).copied(), points.get(&b).copied())
+        );
+        assert_ne!(a, b);
+        assert_eq!(set.closest().unwrap().distance(), expected.distance());
+    }
+
+    #[test]
+    fn randomized_inserts_and_removes() {
+        let mut rng = StdRng::seed_from_u64(0xD1A1);
+        for &distribution in generators::Distribution::ALL.iter() {
+            let pool = distribution.generate(&mut rng, 600);
+            let mut set = DynamicClosestPair::new();
+            let mut points = FnvHashMap::default();
+            let mut handles = Vec::new();
+
+            // Grow to a few hundred points, then shrink back to none
+            for step in 0..1_200 {
+                let grow = step < 600;
+                if handles.is_empty() || rng.gen_bool(if grow { 0.7 } else { 0.3 }) {
+                    let point = pool[rng.gen_range(0..pool.len())];
+                    let handle = set.insert(point);
+                    points.insert(handle, point);
+                    handles.push(handle);
+                } else {
+                    let handle = handles.swap_remove(rng.gen_range(0..handles.len()));
+                    assert_eq!(set.remove(handle), points.remove(&handle));
+                    assert_eq!(set.remove(handle), None);
+                }
+                check(&set, &points);
+            }
+            while let Some(handle) = handles.pop() {
+                set.remove(handle);
+                points.remove(&handle);
+                check(&set, &points);
+            }
+        }
+    }
+
+    #[test]
+    fn sliding_window_matches_bruteforce() {
+        let mut rng = StdRng::seed_from_u64(0x5_11DE);
+        let points = generators::uniform(&mut rng, 1_000);
+        // Points arrive every 0 to 0.2 seconds
+        let mut times = Vec::new();
+        let mut time = 0.0;
+        for _ in &points {
+            time += rng.gen_range(0.0..0.2);
+            times.push(time);
+        }
+
+        for &(max_points, max_age) in &[(Some(50), None), (None, Some(3.0)), (Some(40), Some(2.0))]
+        {
+            let mut window = SlidingWindow::new(max_points, max_age);
+            for (i, (&point, &time)) in points.iter().zip(&times).enumerate() {
+                window.push(point, time);
+
+                let newest = (0..=i)
+                    .rev()
+                    .take(max_points.unwrap_or(usize::MAX))
+                    .take_while(|&j| max_age.is_none_or(|age| time - times[j] < age))
+                    .map(|j| points[j])
+                    .collect::<Vec<_>>();
+                assert_eq!(window.len(), newest.len());
+                if newest.len() < 2 {
+                    assert!(window.closest().is_none());
+                } else {
+                    assert_eq!(
+                        window.closest().unwrap().distance(),
+                        find_minimum_bruteforce(&newest).distance()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn removing_the_closest_pair() {
+        // Repeatedly removing one of the closest pair exercises the searches after a removal
+        let mut rng = StdRng::seed_from_u64(0x0C10_5E57);
+        let mut set = DynamicClosestPair::new();
+        let mut points = FnvHashMap::default();
+        for point in generators::uniform(&mut rng, 300) {
+            points.insert(set.insert(point), point);
+        }
+
+        while set.len() > 1 {
+            let (a, _) = set.closest_handles().unwrap();
+            set.remove(a);
+            points.remove(&a);
+            check(&set, &points);
+        }
+    }
+
+    /// Inserts every point, then removes one of the closest pair until half are left, checking the
+    /// closest pair against the plane sweep every so often.
+    /// # Returns
+    /// Function returns the mean number of points the searches looked at per update.
+    fn scanned_per_update(points: &[Point]) -> f64 {
+        let mut set = DynamicClosestPair::new();
+        let mut handles = FnvHashMap::default();
+        let every = points.len() / 8;
+        let check = |set: &DynamicClosestPair, handles: &FnvHashMap<Handle, Point>| {
+            let expected =
+                Algorithm::PlaneSweep.find_closest_pair(handles.values().copied().collect());
+            assert_eq!(set.closest().unwrap().distance(), expected.distance());
+        };
+
+        for (i, &point) in points.iter().enumerate() {
+            handles.insert(set.insert(point), point);
+            if i > 0 && i % every == 0 {
+                check(&set, &handles);
+            }
+        }
+        for i in 0..points.len() / 2 {
+            let (a, b) = set.closest_handles().unwrap();
+            assert_ne!(a, b);
+            set.remove(a);
+            handles.remove(&a);
+            if i % every == 0 {
+                check(&set, &handles);
+            }
+        }
+        set.scanned as f64 / (points.len() + points.len() / 2) as f64
+    }
+
+    #[test]
+    fn copies_are_not_searched() {
+        let point = Point { x: -0.0, y: 3.5 };
+        let mut points = vec![point; 100_000];
+        points[1].x = 0.0;
+        assert_eq!(scanned_per_update(&points), 0.0);
+
+        // One distinct point for every 10, so most updates only add or remove a copy
+        let mut rng = StdRng::seed_from_u64(0xC0_9135);
+        for &n in &[2_000, 32_000] {
+            let points = generators::Distribution::Duplicates.generate(&mut rng, n);
+            let scanned = scanned_per_update(&points);
+            assert!(scanned < 10.0, "{} points: {} per update", n, scanned);
+        }
+    }
+
+    #[test]
+    fn dense_cluster_among_sparse_points() {
+        // Half of the points in a unit square, among points spread over the whole range
+        let mut rng = StdRng::seed_from_u64(0xDE_25E);
+        let mut per_update = Vec::new();
+        for &n in &[2_000, 32_000] {
+            let mut points = generators::uniform(&mut rng, n / 2);
+            points.extend((0..n / 2).map(|_| Point {
+                x: rng.gen_range(0.0..1.0),
+                y: rng.gen_range(0.0..1.0),
+            }));
+            points.shuffle(&mut rng);
+            per_update.push(scanned_per_update(&points));
+        }
+
+        // A single mesh size made either half scan `O(n)` points per update
+        assert!(
+            per_update.iter().all(|&s| s < 150.0) && per_update[1] < 2.0 * per_update[0],
+            "{:?} points per update",
+            per_update
+        );
+    }
+
+    #[test]
+    fn sliding_window_of_repeated_readings() {
+        // A few sensors that keep reporting the same positions
+        let sensors = [
+            Point { x: 1.0, y: 1.0 },
+            Point { x: 4.0, y: 5.0 },
+            Point { x: -2.0, y: 7.5 },
+        ];
+        let mut window = SlidingWindow::new(Some(10_000), None);
+        for i in 0..100_000 {
+            window.push(sensors[i % sensors.len()], i as f64);
+            let expected = if i < sensors.len() { 5.0 } else { 0.0 };
+            if i > 0 {
+                assert_eq!(window.closest().unwrap().distance().0, expected);
+            }
+        }
+        assert_eq!(window.len(), 10_000);
+        assert!(window.set.scanned <= sensors.len() * sensors.len());
+    }
+}
@@ -0,0 +1,325 @@
+use crate::geometry::Point;
+use rand::prelude::*;
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+/// Half the edge length of the box that clusters, lattices, lines and circles are placed in.
+const EXTENT: f64 = 1e9;
+
+/// A named distribution of points.
+///
+/// Each distribution uses a set of default parameters; use the free functions in this module to
+/// generate points with other parameters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Distribution {
+    /// See [`uniform`].
+    Uniform,
+    /// See [`gaussian_clusters`], with 10 clusters of standard deviation `1e6`.
+    Clustered,
+    /// See [`lattice`], with a spacing of `1e3` and a jitter of `1e2`.
+    Lattice,
+    /// See [`line`].
+    Line,
+    /// See [`circle`].
+    Circle,
+    /// See [`duplicates`], with one distinct point for every 10 points.
+    Duplicates,
+    /// See [`columns`], with 8 columns.
+    Columns,
+    /// See [`quick_select_worst`].
+    QuickSelectWorst,
+    /// See [`strip_worst`].
+    StripWorst,
+}
+
+impl Distribution {
+    /// All distributions, in the order they are listed in [`Distribution::name`].
+    pub const ALL: [Distribution; 9] = [
+        Distribution::Uniform,
+        Distribution::Clustered,
+        Distribution::Lattice,
+        Distribution::Line,
+        Distribution::Circle,
+        Distribution::Duplicates,
+        Distribution::Columns,
+        Distribution::QuickSelectWorst,
+        Distribution::StripWorst,
+    ];
+
+    /// The name of the distribution, as accepted by `FromStr`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Distribution::Uniform => "uniform",
+            Distribution::Clustered => "clustered",
+            Distribution::Lattice => "lattice",
+            Distribution::Line => "line",
+            Distribution::Circle => "circle",
+            Distribution::Duplicates => "duplicates",
+            Distribution::Columns => "columns",
+            Distribution::QuickSelectWorst => "quick-select-worst",
+            Distribution::StripWorst => "strip-worst",
+        }
+    }
+
+    /// Generate `count` points from this distribution.
+    pub fn generate(self, rng: &mut impl Rng, count: usize) -> Vec<Point> {
+        match self {
+            Distribution::Uniform => uniform(rng, count),
+            Distribution::Clustered => gaussian_clusters(rng, count, 10, 1e6),
+            Distribution::Lattice => lattice(rng, count, 1e3, 1e2),
+            Distribution::Line => line(rng, count),
+            Distribution::Circle => circle(rng, count),
+            Distribution::Duplicates => duplicates(rng, count, (count / 10).max(1)),
+            Distribution::Columns => columns(rng, count, 8),
+            Distribution::QuickSelectWorst => quick_select_worst(rng, count),
+            Distribution::StripWorst => strip_worst(rng, count),
+        }
+    }
+
+    /// Generate the same `count` points as [`generate`](Distribution::generate), one at a time.
+    ///
+    /// Uniform, line and circle points are drawn as they are read, in constant memory. The other
+    /// distributions need the whole set before the first point is known, such as to shuffle it, and
+    /// are generated in full first.
+    pub fn stream<'a, R: Rng>(
+        self,
+        rng: &'a mut R,
+        count: usize,
+    ) -> Box<dyn Iterator<Item = Point> + 'a> {
+        match self {
+            Distribution::Uniform => Box::new((0..count).map(move |_| uniform_point(rng))),
+            Distribution::Line => {
+                let slope = line_slope(rng);
+                Box::new((0..count).map(move |_| line_point(rng, slope)))
+            }
+            Distribution::Circle => Box::new((0..count).map(move |_| circle_point(rng))),
+            _ => Box::new(self.generate(rng, count).into_iter()),
+        }
+    }
+}
+
+impl FromStr for Distribution {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Distribution::ALL
+            .iter()
+            .copied()
+            .find(|d| d.name() == s)
+            .ok_or(())
+    }
+}
+
+/// Draws a sample from the standard normal distribution using the Box-Muller transform.
+fn standard_normal(rng: &mut impl Rng) -> f64 {
+    // 1 - [0, 1) is in (0, 1], so the logarithm is always finite
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// Points with both coordinates drawn uniformly from the integers representable by an `i64`.
+///
+/// This is the distribution `make_points` has always used, and is kept identical so that the test
+/// vector in the README can be reproduced.
+pub fn uniform(rng: &mut impl Rng, count: usize) -> Vec<Point> {
+    (0..count).map(|_| uniform_point(rng)).collect()
+}
+
+/// Draws one point of [`uniform`].
+fn uniform_point(rng: &mut impl Rng) -> Point {
+    Point {
+        x: rng.gen::<i64>() as f64,
+        y: rng.gen::<i64>() as f64,
+    }
+}
+
+/// Points drawn from `clusters` gaussian clusters with standard deviation `sigma`, whose centres
+/// are placed uniformly in a box.
+/// # Panics
+/// Function will panic if `clusters` is zero.
+pub fn gaussian_clusters(
+    rng: &mut impl Rng,
+    count: usize,
+    clusters: usize,
+    sigma: f64,
+) -> Vec<Point> {
+    let centres = (0..clusters)
+        .map(|_| Point {
+            x: rng.gen_range(-EXTENT..EXTENT),
+            y: rng.gen_range(-EXTENT..EXTENT),
+        })
+        .collect::<Vec<_>>();
+
+    (0..count)
+        .map(|_| {
+            let c = centres.choose(rng).unwrap();
+            Point {
+                x: c.x + sigma * standard_normal(rng),
+                y: c.y + sigma * standard_normal(rng),
+            }
+        })
+        .collect()
+}
+
+/// Points on a square lattice with the given `spacing`, each moved by up to `jitter` along both
+/// axes. The lattice is filled row by row and the points are shuffled.
+pub fn lattice(rng: &mut impl Rng, count: usize, spacing: f64, jitter: f64) -> Vec<Point> {
+    let side = (count as f64).sqrt().ceil() as usize;
+
+    let mut points = (0..count)
+        .map(|i| {
+            let (jx, jy) = if jitter > 0.0 {
+                (
+                    rng.gen_range(-jitter..jitter),
+                    rng.gen_range(-jitter..jitter),
+                )
+            } else {
+                (0.0, 0.0)
+            };
+            Point {
+                x: (i % side) as f64 * spacing + jx,
+                y: (i / side) as f64 * spacing + jy,
+            }
+        })
+        .collect::<Vec<_>>();
+    points.shuffle(rng);
+    points
+}
+
+/// Collinear points on a random line through the origin.
+pub fn line(rng: &mut impl Rng, count: usize) -> Vec<Point> {
+    let slope = line_slope(rng);
+    (0..count).map(|_| line_point(rng, slope)).collect()
+}
+
+/// Draws the slope of the line of [`line`].
+fn line_slope(rng: &mut impl Rng) -> f64 {
+    rng.gen_range(-10.0..10.0)
+}
+
+/// Draws one point of [`line`] on the line of a given slope.
+fn line_point(rng: &mut impl Rng, slope: f64) -> Point {
+    let x = rng.gen_range(-EXTENT..EXTENT);
+    Point { x, y: slope * x }
+}
+
+/// Points on a circle centred on the origin.
+pub fn circle(rng: &mut impl Rng, count: usize) -> Vec<Point> {
+    (0..count).map(|_| circle_point(rng)).collect()
+}
+
+/// Draws one point of [`circle`].
+fn circle_point(rng: &mut impl Rng) -> Point {
+    let theta = rng.gen_range(0.0..(2.0 * PI));
+    Point {
+        x: EXTENT * theta.cos(),
+        y: EXTENT * theta.sin(),
+    }
+}
+
+/// Points sampled with replacement from `distinct` uniform points, so most points have at least
+/// one exact duplicate.
+/// # Panics
+/// Function will panic if `distinct` is zero.
+pub fn duplicates(rng: &mut impl Rng, count: usize, distinct: usize) -> Vec<Point> {
+    let pool = uniform(rng, distinct);
+    (0..count).map(|_| *pool.choose(rng).unwrap()).collect()
+}
+
+/// Points on `columns` vertical lines, with `x` drawn from `columns` values and `y` drawn uniformly
+/// as in [`uniform`].
+///
+/// Many points share each x-coordinate, which unbalances a two-way partition by `x`. With one
+/// column, every point has the same x-coordinate.
+/// # Panics
+/// Function will panic if `columns` is zero.
+pub fn columns(rng: &mut impl Rng, count: usize, columns: usize) -> Vec<Point> {
+    let xs = (0..columns)
+        .map(|_| rng.gen::<i64>() as f64)
+        .collect::<Vec<_>>();
+    (0..count)
+        .map(|_| Point {
+            x: *xs.choose(rng).unwrap(),
+            y: rng.gen::<i64>() as f64,
+        })
+        .collect()
+}
+
+/// Points sorted by `x`, rotated right by one.
+///
+/// This drives the median-of-three pivot in `quick_select` to pick the second-largest element on
+/// every partition.
+pub fn quick_select_worst(rng: &mut impl Rng, count: usize) -> Vec<Point> {
+    let mut points = (0..count)
+        .map(|i| Point {
+            x: i as f64,
+            y: rng.gen::<i64>() as f64,
+        })
+        .collect::<Vec<_>>();
+    points.rotate_right(1);
+    points
+}
+
+/// Points in a narrow vertical band, spaced one unit apart along `y`.
+///
+/// The closest pair distance is at least one, but every point is within that distance of the
+/// median `x`, so every point falls into the strip at every level of the divide-and-conquer
+/// algorithms.
+pub fn strip_worst(rng: &mut impl Rng, count: usize) -> Vec<Point> {
+    let mut points = (0..count)
+        .map(|i| Point {
+            x: rng.gen_range(-0.25..0.25),
+            y: i as f64,
+        })
+        .collect::<Vec<_>>();
+    points.shuffle(rng);
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_test_vector() {
+        let mut rng = StdRng::seed_from_u64(0xABAB_BABA);
+        let points = uniform(&mut rng, 2);
+
+        assert_eq!(
+            points,
+            vec![
+                Point {
+                    x: 6626793333355418624.0,
+                    y: -7496458538495980544.0
+                },
+                Point {
+                    x: -8007577507249937408.0,
+                    y: 278987807760401.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn distributions_generate_count() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for d in Distribution::ALL.iter() {
+            let points = d.generate(&mut rng, 100);
+            assert_eq!(points.len(), 100, "{}", d.name());
+            assert!(points.iter().all(|p| p.x.is_finite() && p.y.is_finite()));
+            assert_eq!(d.name().parse::<Distribution>(), Ok(*d));
+        }
+    }
+    #[test]
+    fn stream_matches_generate() {
+        for d in Distribution::ALL.iter() {
+            let generated = d.generate(&mut StdRng::seed_from_u64(7), 100);
+            let streamed = d
+                .stream(&mut StdRng::seed_from_u64(7), 100)
+                .collect::<Vec<_>>();
+            assert_eq!(generated, streamed, "{}", d.name());
+        }
+    }
+}
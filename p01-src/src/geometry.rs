@@ -11,6 +11,23 @@ impl Distance {
         Distance(self.0.min(other.0))
     }
 
+    /// Formats a distance according to spec, as `pretty_print` would print it but without the
+    /// trailing newline.
+    pub fn to_spec_string(self) -> String {
+        let f = CString::new("%.9lg").unwrap();
+        let mut buffer = [0_u8; 64];
+        // %.9lg is at most 9 significant digits plus sign, point and exponent, which fits
+        let len = unsafe {
+            libc::snprintf(
+                buffer.as_mut_ptr() as *mut libc::c_char,
+                buffer.len(),
+                f.as_ptr(),
+                self.0,
+            )
+        };
+        String::from_utf8_lossy(&buffer[..(len.max(0) as usize).min(buffer.len() - 1)]).into_owned()
+    }
+
     /// Helper function to pretty-print a distance according to spec.
     /// # Safety
     /// The print has failed if a negative value is returned.
@@ -74,6 +91,13 @@ mod tests {
         assert_eq!(a.distance_to(b), Distance(0_f64))
     }
 
+    #[test]
+    fn distance_spec_string() {
+        assert_eq!(Distance(5.0).to_spec_string(), "5");
+        assert_eq!(Distance(2_f64.sqrt()).to_spec_string(), "1.41421356");
+        assert_eq!(Distance(1.5e20).to_spec_string(), "1.5e+20");
+    }
+
     #[test]
     fn points_distance_negative() {
         let a = Point { x: 0.0, y: 0.0 };
@@ -0,0 +1,407 @@
+use crate::geometry::{Point, PointPair};
+use crate::quick_select::select_by_key;
+use std::ops::Range;
+
+/// The coordinate of a point along the `y` axis if `on_y`, or else along the `x` axis.
+fn coordinate(point: Point, on_y: bool) -> f64 {
+    if on_y {
+        point.y
+    } else {
+        point.x
+    }
+}
+
+/// A 2-d tree over a fixed list of points, for repeated nearest neighbour and range queries.
+///
+/// The tree is implicit in the order of its points. The root of every subtree is the middle point
+/// of its slice, placed there with [`select_by_key`] so that the points before it are no larger
+/// along the splitting axis and the points after it are no smaller. Each subtree splits on the
+/// axis its points are most spread out along, so that inputs such as points in a narrow strip are
+/// still split into compact regions. Building the tree takes `O(n log n)` time in the worst case.
+#[derive(Clone, Debug, Default)]
+pub struct KdTree {
+    points: Vec<Point>,
+    /// Whether the subtree rooted at each point splits on `y` rather than `x`.
+    on_y: Vec<bool>,
+}
+
+/// The closest point to a query found so far, its distance, and the number of points looked at.
+struct Closest {
+    distance: f64,
+    point: Option<Point>,
+    scanned: usize,
+}
+
+impl KdTree {
+    /// Build a tree containing `points`.
+    /// # Panics
+    /// Function will panic if any coordinate is NaN.
+    pub fn new(mut points: Vec<Point>) -> Self {
+        let mut on_y = vec![false; points.len()];
+        KdTree::build(&mut points, &mut on_y);
+        Self { points, on_y }
+    }
+
+    /// Rearranges a subtree so that its root is its median along the axis it is most spread out
+    /// along, recording that axis at the root.
+    fn build(points: &mut [Point], on_y: &mut [bool]) {
+        if points.len() < 2 {
+            return;
+        }
+        let spread = |c: fn(&Point) -> f64| {
+            let (low, high) = points
+                .iter()
+                .map(c)
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), v| {
+                    (low.min(v), high.max(v))
+                });
+            high - low
+        };
+        let middle = points.len() / 2;
+        let axis = spread(|p| p.y) > spread(|p| p.x);
+        on_y[middle] = axis;
+
+        let (left, right) = select_by_key(points, middle, |p| coordinate(*p, axis));
+        let (left_on_y, right_on_y) = on_y.split_at_mut(middle);
+        KdTree::build(left, left_on_y);
+        KdTree::build(&mut right[1..], &mut right_on_y[1..]);
+    }
+
+    /// The number of points in the tree.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns whether the tree has no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Returns every point in the tree, in the order of the tree.
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    /// Returns the root of a subtree, its index, the offset of `point` from it along the axis it
+    /// splits on, and the subtrees on the same side of the split as `point` and on the other.
+    fn split(
+        &self,
+        subtree: &Range<usize>,
+        point: Point,
+    ) -> (Point, usize, f64, Range<usize>, Range<usize>) {
+        let middle = subtree.start + subtree.len() / 2;
+        let root = self.points[middle];
+        let on_y = self.on_y[middle];
+        let offset = coordinate(point, on_y) - coordinate(root, on_y);
+
+        let (left, right) = (subtree.start..middle, middle + 1..subtree.end);
+        if offset < 0.0 {
+            (root, middle, offset, left, right)
+        } else {
+            (root, middle, offset, right, left)
+        }
+    }
+
+    /// Finds the point in the tree closest to `point`, which need not be in the tree itself.
+    /// # Returns
+    /// Function returns `None` if the tree is empty.
+    pub fn nearest(&self, point: Point) -> Option<Point> {
+        self.nearest_kept(point, f64::INFINITY, |_| true).0
+    }
+
+    /// Finds the point in the tree closest to `point` among the points `keep` accepts, if any is
+    /// closer than `within`. Also returns the number of points looked at.
+    pub(crate) fn nearest_kept(
+        &self,
+        point: Point,
+        within: f64,
+        keep: impl Fn(Point) -> bool,
+    ) -> (Option<Point>, usize) {
+        let mut closest = Closest {
+            distance: within,
+            point: None,
+            scanned: 0,
+        };
+        self.nearest_in(0..self.len(), point, &|_, p| keep(p), &mut closest);
+        (closest.point, closest.scanned)
+    }
+
+    /// Searches a subtree for a point closer to `point` than `closest`. Points that `keep` does
+    /// not accept, given their index and position, are left out, so that a point in the tree is
+    /// not found as its own neighbour.
+    fn nearest_in(
+        &self,
+        subtree: Range<usize>,
+        point: Point,
+        keep: &impl Fn(usize, Point) -> bool,
+        closest: &mut Closest,
+    ) {
+        if subtree.is_empty() {
+            return;
+        }
+        let (root, index, offset, near, far) = self.split(&subtree, point);
+        closest.scanned += 1;
+        let distance = root.distance_to(point).0;
+        if distance < closest.distance && keep(index, root) {
+            closest.distance = distance;
+            closest.point = Some(root);
+        }
+
+        // The side of the split that `point` is on most likely holds the closest point, and
+        // nothing on the other side is closer than the split itself
+        self.nearest_in(near, point, keep, closest);
+        if offset.abs() < closest.distance {
+            self.nearest_in(far, point, keep, closest);
+        }
+    }
+
+    /// Finds the `k` points in the tree closest to `point`, closest first. Ties are broken
+    /// arbitrarily.
+    /// # Returns
+    /// Function returns every point in the tree if there are fewer than `k`.
+    pub fn k_nearest(&self, point: Point, k: usize) -> Vec<Point> {
+        let mut nearest = Vec::with_capacity(k.min(self.len()));
+        if k > 0 {
+            self.k_nearest_in(0..self.len(), point, k, &mut nearest);
+        }
+        nearest.into_iter().map(|(_, p)| p).collect()
+    }
+
+    /// Searches a subtree for points closer to `point` than the furthest of `nearest`, keeping the
+    /// closest `k` sorted by distance.
+    fn k_nearest_in(
+        &self,
+        subtree: Range<usize>,
+        point: Point,
+        k: usize,
+        nearest: &mut Vec<(f64, Point)>,
+    ) {
+        if subtree.is_empty() {
+            return;
+        }
+        let furthest = |nearest: &Vec<(f64, Point)>| {
+            if nearest.len() < k {
+                f64::INFINITY
+            } else {
+                nearest[k - 1].0
+            }
+        };
+
+        let (root, _, offset, near, far) = self.split(&subtree, point);
+        let distance = root.distance_to(point).0;
+        if distance < furthest(nearest) {
+            if nearest.len() == k {
+                nearest.pop();
+            }
+            let at = nearest.partition_point(|&(d, _)| d <= distance);
+            nearest.insert(at, (distance, root));
+        }
+
+        self.k_nearest_in(near, point, k, nearest);
+        if offset.abs() < furthest(nearest) {
+            self.k_nearest_in(far, point, k, nearest);
+        }
+    }
+
+    /// Returns every point in the tree within `radius` of `centre`, inclusive, in no particular
+    /// order.
+    pub fn within_radius(&self, centre: Point, radius: f64) -> Vec<Point> {
+        let from = Point {
+            x: centre.x - radius,
+            y: centre.y - radius,
+        };
+        let to = Point {
+            x: centre.x + radius,
+            y: centre.y + radius,
+        };
+        let mut found = Vec::new();
+        self.in_rectangle_in(0..self.len(), from, to, &mut |p| {
+            if p.distance_to(centre).0 <= radius {
+                found.push(p);
+            }
+        });
+        found
+    }
+
+    /// Returns every point in the tree inside the axis-aligned rectangle with corners `from` and
+    /// `to`, inclusive, in no particular order.
+    pub fn in_rectangle(&self, from: Point, to: Point) -> Vec<Point> {
+        let mut found = Vec::new();
+        self.in_rectangle_in(0..self.len(), from, to, &mut |p| found.push(p));
+        found
+    }
+
+    /// Calls `found` with every point of a subtree inside the rectangle with corners `from` and
+    /// `to`.
+    fn in_rectangle_in(
+        &self,
+        subtree: Range<usize>,
+        from: Point,
+        to: Point,
+        found: &mut impl FnMut(Point),
+    ) {
+        if subtree.is_empty() {
+            return;
+        }
+        let middle = subtree.start + subtree.len() / 2;
+        let root = self.points[middle];
+        if from.x <= root.x && root.x <= to.x && from.y <= root.y && root.y <= to.y {
+            found(root);
+        }
+
+        let on_y = self.on_y[middle];
+        let split = coordinate(root, on_y);
+        if coordinate(from, on_y) <= split {
+            self.in_rectangle_in(subtree.start..middle, from, to, found);
+        }
+        if split <= coordinate(to, on_y) {
+            self.in_rectangle_in(middle + 1..subtree.end, from, to, found);
+        }
+    }
+
+    /// Finds the closest pair of points in the tree, by searching for the nearest other point to
+    /// every point. Each search starts from the closest distance found so far, so most of the tree
+    /// is pruned.
+    /// # Returns
+    /// Function returns `None` if there are fewer than two points.
+    pub fn closest_pair(&self) -> Option<PointPair> {
+        let mut closest = Closest {
+            distance: f64::INFINITY,
+            point: None,
+            scanned: 0,
+        };
+        let mut pair = None;
+        for (index, &point) in self.points.iter().enumerate() {
+            closest.point = None;
+            self.nearest_in(0..self.len(), point, &|i, _| i != index, &mut closest);
+            if let Some(other) = closest.point {
+                pair = Some(PointPair(point, other));
+            }
+        }
+        pair
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::closest::find_minimum_bruteforce;
+    use crate::generators;
+    use rand::prelude::*;
+
+    /// Each distribution, with a tree over its points.
+    fn trees(rng: &mut StdRng) -> Vec<(Vec<Point>, KdTree)> {
+        generators::Distribution::ALL
+            .iter()
+            .map(|d| {
+                let points = d.generate(rng, 500);
+                (points.clone(), KdTree::new(points))
+            })
+            .collect()
+    }
+
+    /// A random point on the line between two of the points.
+    fn query(rng: &mut StdRng, points: &[Point]) -> Point {
+        let p = points[rng.gen_range(0..points.len())];
+        let q = points[rng.gen_range(0..points.len())];
+        let t = rng.gen::<f64>();
+        Point {
+            x: p.x + t * (q.x - p.x),
+            y: p.y + t * (q.y - p.y),
+        }
+    }
+
+    fn sorted(mut points: Vec<Point>) -> Vec<Point> {
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        points
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = KdTree::new(Vec::new());
+        let origin = Point { x: 0.0, y: 0.0 };
+        assert_eq!(tree.nearest(origin), None);
+        assert!(tree.k_nearest(origin, 3).is_empty());
+        assert!(tree.closest_pair().is_none());
+    }
+
+    #[test]
+    fn nearest_matches_bruteforce() {
+        let mut rng = StdRng::seed_from_u64(0xCD7E);
+        for (points, tree) in trees(&mut rng) {
+            for i in 0..100 {
+                // Half of the queries are points in the tree
+                let q = if i % 2 == 0 {
+                    points[i]
+                } else {
+                    query(&mut rng, &points)
+                };
+                let mut distances = points
+                    .iter()
+                    .map(|p| p.distance_to(q).0)
+                    .collect::<Vec<_>>();
+                distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+                assert_eq!(
+                    tree.nearest(q).map(|p| p.distance_to(q).0),
+                    Some(distances[0])
+                );
+                for &k in [1, 5, 600].iter() {
+                    let found = tree
+                        .k_nearest(q, k)
+                        .iter()
+                        .map(|p| p.distance_to(q).0)
+                        .collect::<Vec<_>>();
+                    assert_eq!(found, distances[..k.min(distances.len())]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn radius_and_rectangle_match_bruteforce() {
+        let mut rng = StdRng::seed_from_u64(0x2AD2);
+        for (points, tree) in trees(&mut rng) {
+            for _ in 0..50 {
+                let (a, b) = (query(&mut rng, &points), query(&mut rng, &points));
+
+                let radius = a.distance_to(b).0 / 4.0;
+                let expected = points
+                    .iter()
+                    .filter(|p| p.distance_to(a).0 <= radius)
+                    .copied()
+                    .collect();
+                assert_eq!(sorted(tree.within_radius(a, radius)), sorted(expected));
+
+                let from = Point {
+                    x: a.x.min(b.x),
+                    y: a.y.min(b.y),
+                };
+                let to = Point {
+                    x: a.x.max(b.x),
+                    y: a.y.max(b.y),
+                };
+                let expected = points
+                    .iter()
+                    .filter(|p| from.x <= p.x && p.x <= to.x && from.y <= p.y && p.y <= to.y)
+                    .copied()
+                    .collect();
+                assert_eq!(sorted(tree.in_rectangle(from, to)), sorted(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn closest_pair_matches_bruteforce() {
+        let mut rng = StdRng::seed_from_u64(0xC105);
+        for (points, tree) in trees(&mut rng) {
+            assert_eq!(
+                tree.closest_pair().map(|p| p.distance()),
+                Some(find_minimum_bruteforce(&points).distance())
+            );
+        }
+        assert!(KdTree::new(vec![Point { x: 1.0, y: 1.0 }])
+            .closest_pair()
+            .is_none());
+    }
+}
@@ -1,14 +1,21 @@
+pub mod check;
+pub mod cli;
 pub mod closest;
+pub mod dynamic;
+pub mod generators;
 pub mod geometry;
+pub mod kdtree;
 pub mod parse;
 pub mod quick_select;
+pub mod spatial;
+pub mod verify;
 
 /// Bails out an error, printing a message to stdout, and then quit the process.
 #[macro_export]
 macro_rules! bail_error {
     ($exit_code : expr) => {{
         use std::process;
-        println!("{}", "Incorrectly formatted input");
+        println!("{}", $crate::check::MALFORMED_INPUT);
         process::exit($exit_code)
     }};
 }
@@ -1,25 +1,168 @@
+use closest_pairs::cli::{parse_u64, Args};
+use closest_pairs::generators::Distribution;
 use closest_pairs::geometry::Point;
 use rand::prelude::*;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
+use std::process;
+use std::str::FromStr;
 
-const SIZE: usize = 10_000_000;
-const SEED: u64 = 0xABAB_BABA;
-const FILE_LOCATION: &str = "../stacs/testing.in";
+const DEFAULT_COUNT: usize = 10_000_000;
+const DEFAULT_SEED: u64 = 0xABAB_BABA;
 
-fn main() {
-    let mut rng = StdRng::seed_from_u64(SEED);
-    let file = File::create(FILE_LOCATION).unwrap();
-    let mut bw = BufWriter::new(file);
+const USAGE: &str = "\
+Usage: make_points [--count N] [--seed SEED] [--output PATH] [--format text|binary|csv]
+                   [--distribution NAME]
 
-    writeln!(&mut bw, "{}", SIZE).unwrap();
+  --count N            Number of points to generate (default: 10000000)
+  --seed SEED          Seed for the PRNG, decimal or 0x-prefixed hex (default: 0xABAB_BABA)
+  --output PATH        File to write to (default: stdout)
+  --format FMT         Output format (default: text)
+  --distribution NAME  One of uniform, clustered, lattice, line, circle, duplicates,
+                       columns, quick-select-worst, strip-worst (default: uniform)";
 
-    (0..SIZE)
-        .map(|_| Point {
-            x: rng.gen::<i64>() as f64,
-            y: rng.gen::<i64>() as f64,
-        })
-        .for_each(|p| {
-            writeln!(&mut bw, "{:.1} {:.1}", p.x, p.y).unwrap();
+/// Output format of the generated points.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Format {
+    /// The format specified by the spec: the number of points, then one `x y` pair per line.
+    Text,
+    /// The number of points as a little-endian `u64`, then each `x` and `y` as little-endian `f64`.
+    Binary,
+    /// A header line `x,y`, then one `x,y` pair per line.
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "binary" => Ok(Format::Binary),
+            "csv" => Ok(Format::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Command-line configuration.
+struct Config {
+    count: usize,
+    seed: u64,
+    output: Option<String>,
+    format: Format,
+    distribution: Distribution,
+}
+
+impl Config {
+    fn from_args() -> Result<Self, String> {
+        let args = Args::parse(
+            std::env::args().skip(1),
+            &["count", "seed", "output", "format", "distribution"],
+            &[],
+        )
+        .map_err(|e| e.to_string())?;
+
+        if let Some(arg) = args.positional().first() {
+            return Err(format!("unexpected argument `{}`", arg));
+        }
+
+        Ok(Self {
+            count: args
+                .value_with("count", |s| parse_u64(s).map(|c| c as usize))
+                .map_err(|e| e.to_string())?
+                .unwrap_or(DEFAULT_COUNT),
+            seed: args
+                .value_with("seed", parse_u64)
+                .map_err(|e| e.to_string())?
+                .unwrap_or(DEFAULT_SEED),
+            output: args.value("output").map_err(|e| e.to_string())?,
+            format: args
+                .value("format")
+                .map_err(|e| e.to_string())?
+                .unwrap_or(Format::Text),
+            distribution: args
+                .value("distribution")
+                .map_err(|e| e.to_string())?
+                .unwrap_or(Distribution::Uniform),
         })
+    }
+}
+
+/// Formats a coordinate so that it can be read back exactly.
+///
+/// Integral values keep the `{:.1}` format that the README test vector was generated with; other
+/// values use the shortest representation that round-trips.
+fn coordinate(v: f64) -> String {
+    if v.fract() == 0.0 {
+        format!("{:.1}", v)
+    } else {
+        format!("{}", v)
+    }
+}
+
+/// Writes all points to `w` in the given format.
+fn write_points(
+    w: &mut impl Write,
+    format: Format,
+    count: usize,
+    points: impl Iterator<Item = Point>,
+) -> io::Result<()> {
+    match format {
+        Format::Text => {
+            writeln!(w, "{}", count)?;
+            for p in points {
+                writeln!(w, "{} {}", coordinate(p.x), coordinate(p.y))?;
+            }
+        }
+        Format::Binary => {
+            w.write_all(&(count as u64).to_le_bytes())?;
+            for p in points {
+                w.write_all(&p.x.to_le_bytes())?;
+                w.write_all(&p.y.to_le_bytes())?;
+            }
+        }
+        Format::Csv => {
+            writeln!(w, "x,y")?;
+            for p in points {
+                writeln!(w, "{},{}", coordinate(p.x), coordinate(p.y))?;
+            }
+        }
+    }
+    w.flush()
+}
+
+fn main() {
+    let config = Config::from_args().unwrap_or_else(|e| {
+        eprintln!("error: {}\n\n{}", e, USAGE);
+        process::exit(2)
+    });
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let points = config.distribution.stream(&mut rng, config.count);
+
+    let result = match &config.output {
+        Some(path) => {
+            let file = File::create(path).unwrap_or_else(|e| {
+                eprintln!("error: could not create `{}`: {}", path, e);
+                process::exit(1)
+            });
+            write_points(
+                &mut BufWriter::new(file),
+                config.format,
+                config.count,
+                points,
+            )
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut bw = BufWriter::new(stdout.lock());
+            write_points(&mut bw, config.format, config.count, points)
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1)
+    }
 }
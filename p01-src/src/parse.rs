@@ -1,9 +1,10 @@
 use crate::geometry::Point;
-use nom::character::complete::{digit1, newline, space1};
-use nom::combinator::map_res;
+use nom::character::complete::{digit1, newline, space0, space1};
+use nom::combinator::{eof, map_res, opt};
 use nom::error::ErrorKind;
 use nom::multi::many1;
 use nom::number::complete::double;
+use nom::sequence::preceded;
 use nom::Err::Error;
 use nom::{error, IResult};
 use std::str::FromStr;
@@ -38,3 +39,39 @@ pub fn parse_stdin(input: &str) -> IResult<&str, Vec<Point>> {
 
     Ok((input, points))
 }
+
+/// Parse a single line of a stream, holding a point and optionally the time it was taken.
+pub fn parse_stream_line(input: &str) -> IResult<&str, (Point, Option<f64>)> {
+    let (input, _) = space0(input)?;
+    let (input, x) = double(input)?;
+    let (input, _) = space1(input)?;
+    let (input, y) = double(input)?;
+    let (input, time) = opt(preceded(space1, double))(input)?;
+    let (input, _) = space0(input)?;
+    let (input, _) = eof(input)?;
+
+    Ok((input, (Point { x, y }, time)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_lines() {
+        let point = |x, y| Point { x, y };
+        assert_eq!(parse_stream_line("1 2"), Ok(("", (point(1.0, 2.0), None))));
+        assert_eq!(
+            parse_stream_line("  -1.5\t2e3  "),
+            Ok(("", (point(-1.5, 2e3), None)))
+        );
+        assert_eq!(
+            parse_stream_line("1 2 3.25"),
+            Ok(("", (point(1.0, 2.0), Some(3.25))))
+        );
+
+        for line in &["", "1", "1 2 3 4", "1 x", "1,2", "x 1 2", "1 2 t"] {
+            assert!(parse_stream_line(line).is_err(), "{:?}", line);
+        }
+    }
+}
@@ -1,37 +1,130 @@
 use crate::geometry::Point;
-use itertools::partition;
 use std::cmp::Ordering;
 
-/// Finds a pivot using the median-of-three strategy.
-///
-/// From the slice `points` provided, this function picks the median value of the first, middle and
-/// last Point and returns the index of the chosen pivot.
-pub fn find_pivot(points: &[Point]) -> usize {
-    let f = &points[0];
-    let m = &points[(points.len() - 1) / 2];
-    let l = &points[points.len() - 1];
+/// The signature shared by the QuickSelect variants.
+pub type Select = fn(&mut [Point], usize) -> (&mut [Point], &mut [Point]);
+
+/// Returns the index of the median of the first, middle and last items by `key`.
+fn median_of_three<T, K: PartialOrd>(items: &[T], key: &impl Fn(&T) -> K) -> usize {
+    let middle = (items.len() - 1) / 2;
+    let last = items.len() - 1;
+    let (f, m, l) = (key(&items[0]), key(&items[middle]), key(&items[last]));
 
     if (f < m && m < l) || (l < m && m < f) {
         // Middle
-        (points.len() - 1) / 2
+        middle
     } else if (m < f && f < l) || (l < f && f < m) {
         // First
         0
     } else {
         // Last
-        points.len() - 1
+        last
+    }
+}
+
+/// Finds a pivot using the median-of-three strategy.
+///
+/// From the slice `points` provided, this function picks the median value of the first, middle and
+/// last Point and returns the index of the chosen pivot.
+pub fn find_pivot(points: &[Point]) -> usize {
+    median_of_three(points, &|p: &Point| *p)
+}
+
+/// Sorts a few items by `key`.
+fn sort_by_key<T, K: PartialOrd>(items: &mut [T], key: &impl Fn(&T) -> K) {
+    items.sort_unstable_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+}
+
+/// Finds a pivot in `items` by `key` using the median-of-medians strategy. See
+/// [`find_pivot_median_of_medians`].
+fn median_of_medians<T, K: PartialOrd>(items: &mut [T], key: &impl Fn(&T) -> K) -> usize {
+    if items.len() <= 5 {
+        sort_by_key(items, key);
+        return (items.len() - 1) / 2;
     }
+
+    let groups = items.len() / 5;
+    for g in 0..groups {
+        sort_by_key(&mut items[g * 5..g * 5 + 5], key);
+        items.swap(g, g * 5 + 2);
+    }
+
+    let median = (groups - 1) / 2;
+    median_of_medians_select(&mut items[..groups], median, key);
+    median
 }
 
-/// This is the inner function that performs the QuickSelect
-fn quick_select_points_inner(mut points: &mut [Point], mut position: usize) {
+/// Finds a pivot using the median-of-medians strategy.
+///
+/// The slice `points` is split into groups of five, the median of each group is moved to the front,
+/// and the median of those medians is selected recursively. This reorders `points` and returns the
+/// index of the chosen pivot. When the x-coordinates are distinct, at least 30% of the points lie on
+/// each side of the pivot.
+pub fn find_pivot_median_of_medians(points: &mut [Point]) -> usize {
+    median_of_medians(points, &|p: &Point| p.x)
+}
+
+/// Moves every item matching `predicate` before every other item, swapping from both ends, and
+/// returns the number of matching items.
+fn partition_by<T>(items: &mut [T], predicate: impl Fn(&T) -> bool) -> usize {
+    let (mut i, mut j) = (0, items.len());
+    loop {
+        while i < j && predicate(&items[i]) {
+            i += 1;
+        }
+        while i < j && !predicate(&items[j - 1]) {
+            j -= 1;
+        }
+        if i == j {
+            return i;
+        }
+        items.swap(i, j - 1);
+        i += 1;
+        j -= 1;
+    }
+}
+
+/// Partitions `items` three ways around the pivot by `key` and narrows `items` and `position` down
+/// to the part that holds `position`. Returns `None` if `position` is among the items equal to the
+/// pivot, which are then already in place.
+///
+/// Grouping the equal items keeps the partitions balanced when many items share a key. The equal
+/// items are split off in a second pass over the larger side, which swaps far less than the
+/// single-pass Dutch national flag algorithm when the keys are distinct, and is skipped when
+/// `position` is among the smaller items.
+fn narrow<'a, T, K: PartialOrd>(
+    items: &'a mut [T],
+    pivot: usize,
+    position: usize,
+    key: &impl Fn(&T) -> K,
+) -> Option<(&'a mut [T], usize)> {
+    let pivot_key = key(&items[pivot]);
+    let lt = partition_by(items, |t| key(t) < pivot_key);
+    if position < lt {
+        return Some((&mut items[..lt], position));
+    }
+
+    let gt = lt + partition_by(&mut items[lt..], |t| key(t) == pivot_key);
+    if position < gt {
+        None
+    } else {
+        Some((&mut items[gt..], position - gt))
+    }
+}
+
+/// This is the inner function that performs the QuickSelect, partitioning two ways around the
+/// pivot of [`find_pivot`]: the pivot is put in its place and the points with an equal x-coordinate
+/// end up on its right, so a run of equal x-coordinates is only shortened by one point per
+/// partition.
+fn quick_select_inner(mut points: &mut [Point], mut position: usize) {
     loop {
         let split = {
             let pivot = find_pivot(points);
-            let pivot_x = points[pivot].x;
-            points.swap(pivot, points.len() - 1);
-            let s = partition(points.iter_mut(), |p| p.x < pivot_x);
-            points.swap(s, points.len() - 1);
+            let last = points.len() - 1;
+            points.swap(pivot, last);
+            let pivot_x = points[last].x;
+            let s = partition_by(&mut points[..last], |p| p.x < pivot_x);
+            points.swap(s, last);
             s
         };
 
@@ -40,29 +133,129 @@ fn quick_select_points_inner(mut points: &mut [Point], mut position: usize) {
                 return;
             }
             Ordering::Greater => {
-                points = &mut points[0..split];
+                points = &mut points[..split];
             }
             Ordering::Less => {
-                position = position - split - 1;
-                points = &mut points[(split + 1)..]
+                position -= split + 1;
+                points = &mut points[split + 1..];
             }
         }
     }
 }
 
+/// QuickSelect with a median-of-medians pivot at every step, which is `O(n)` in the worst case but
+/// slower than a median-of-three pivot on typical inputs.
+fn median_of_medians_select<T, K: PartialOrd>(
+    mut items: &mut [T],
+    mut position: usize,
+    key: &impl Fn(&T) -> K,
+) {
+    loop {
+        let pivot = median_of_medians(items, key);
+
+        match narrow(items, pivot, position, key) {
+            None => return,
+            Some((i, n)) => {
+                items = i;
+                position = n;
+            }
+        }
+    }
+}
+
+/// The number of bad partitions in a row after which [`introselect_inner`] falls back to
+/// median-of-medians pivots.
+const MAX_BAD_PARTITIONS: u32 = 3;
+
+/// Introselect: QuickSelect with median-of-three pivots, switching to median-of-medians pivots
+/// once there have been [`MAX_BAD_PARTITIONS`] bad partitions in a row. A partition is bad when the
+/// part that is kept holds more than seven eighths of the items.
+///
+/// Every run of at most `MAX_BAD_PARTITIONS` bad partitions ends with a good one or the fallback,
+/// so the kept part shrinks to seven eighths of its length at least once every few linear passes,
+/// which bounds the total work by a constant times `n`.
+fn introselect_inner<T, K: PartialOrd>(
+    mut items: &mut [T],
+    mut position: usize,
+    key: &impl Fn(&T) -> K,
+) {
+    let mut bad_partitions = 0;
+
+    loop {
+        if bad_partitions >= MAX_BAD_PARTITIONS {
+            return median_of_medians_select(items, position, key);
+        }
+
+        let length = items.len();
+        let pivot = median_of_three(items, key);
+
+        match narrow(items, pivot, position, key) {
+            None => return,
+            Some((i, n)) => {
+                if i.len() > length - length / 8 {
+                    bad_partitions += 1;
+                } else {
+                    bad_partitions = 0;
+                }
+                items = i;
+                position = n;
+            }
+        }
+    }
+}
+
+/// Performs QuickSelect on a slice of any type, comparing items by `key`.
+///
+/// Rearranges `items` such that the item that would be at `nth` if the slice were sorted by `key`
+/// is at `nth`, every item before it has a key no larger and every item after it has a key no
+/// smaller. Returns the slice split at `nth`, so the selected item is the first of the right part.
+///
+/// This is the introselect behind [`quick_select_points`], and runs in `O(n)` time in the worst
+/// case.
+/// # Panics
+/// Function will panic if `nth` is out of bounds, or if `key` returns values that cannot be
+/// compared, such as NaN.
+pub fn select_by_key<T, K: PartialOrd>(
+    items: &mut [T],
+    nth: usize,
+    key: impl Fn(&T) -> K,
+) -> (&mut [T], &mut [T]) {
+    assert!(nth < items.len(), "nth is out of bounds");
+    introselect_inner(items, nth, &key);
+    items.split_at_mut(nth)
+}
+
 /// Performs QuickSelect on a list of points.
 ///
 /// Given the list `points` and a `position`, the algorithm
 /// will rearrange elements such that the nth largest element is in position specified, such that all
 /// elements larger than it are on the right, and all elements smaller than it are on the left.
+///
+/// This is an introselect: it runs in `O(n)` time in the worst case,
+/// including inputs such as [`generators::quick_select_worst`](crate::generators::quick_select_worst)
+/// that drive [`quick_select_points_median_of_three`] quadratic.
 pub fn quick_select_points(points: &mut [Point], position: usize) -> (&mut [Point], &mut [Point]) {
-    quick_select_points_inner(points, position);
+    select_by_key(points, position, |p| p.x)
+}
+
+/// Performs QuickSelect on a list of points, always using median-of-three pivots.
+///
+/// This is the original QuickSelect, kept for comparison in the benchmarks. The pivot is the median
+/// of three points compared as whole points, by x and then y, and the points are partitioned by x.
+/// It has no worst-case fallback, so it is `O(n^2)` on adversarial inputs.
+pub fn quick_select_points_median_of_three(
+    points: &mut [Point],
+    position: usize,
+) -> (&mut [Point], &mut [Point]) {
+    quick_select_inner(points, position);
     points.split_at_mut(position)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generators;
+    use rand::prelude::*;
 
     #[test]
     fn quick_select_small() {
@@ -120,4 +313,172 @@ mod tests {
         assert!(left.iter().all(|x| x <= median));
         assert!(right.iter().skip(1).all(|x| x >= median));
     }
+
+    /// Checks that `select` puts the point at `position` where `select_nth_unstable_by` does,
+    /// with smaller points to its left and larger points to its right.
+    fn check_select(select: Select, mut points: Vec<Point>, position: usize) {
+        let mut expected = points.clone();
+        let (_, median, _) =
+            expected.select_nth_unstable_by(position, |a, b| a.x.partial_cmp(&b.x).unwrap());
+
+        let (left, right) = select(&mut points, position);
+
+        assert_eq!(right[0].x, median.x);
+        assert!(left.iter().all(|p| p.x <= median.x));
+        assert!(right.iter().all(|p| p.x >= median.x));
+    }
+
+    #[test]
+    fn introselect_matches_std() {
+        let mut rng = StdRng::seed_from_u64(0x5E1E_C7ED);
+        for &count in [1, 2, 5, 6, 24, 25, 26, 100, 1_000, 10_000].iter() {
+            let inputs = vec![
+                generators::uniform(&mut rng, count),
+                generators::quick_select_worst(&mut rng, count),
+                generators::lattice(&mut rng, count, 1.0, 0.0),
+            ];
+            for points in inputs {
+                for &position in [0, count / 2, count - 1].iter() {
+                    check_select(quick_select_points, points.clone(), position);
+                    check_select(
+                        quick_select_points_median_of_three,
+                        points.clone(),
+                        position,
+                    );
+                    check_select(
+                        |p, n| {
+                            median_of_medians_select(p, n, &|p: &Point| p.x);
+                            p.split_at_mut(n)
+                        },
+                        points.clone(),
+                        position,
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn introselect_is_linear() {
+        let mut rng = StdRng::seed_from_u64(0x11EA);
+        let count = 100_000;
+        for points in [
+            generators::uniform(&mut rng, count),
+            generators::quick_select_worst(&mut rng, count),
+        ] {
+            let calls = std::cell::Cell::new(0);
+            let mut points = points;
+            select_by_key(&mut points, count / 2, |p| {
+                calls.set(calls.get() + 1);
+                p.x
+            });
+            // Falling back after log2(n) bad partitions took over 20 calls per item here
+            assert!(calls.get() < 20 * count, "{} key calls", calls.get());
+        }
+    }
+
+    #[test]
+    fn select_with_equal_x() {
+        let mut rng = StdRng::seed_from_u64(0xE0A1);
+        for &count in [1, 2, 7, 100, 10_000].iter() {
+            for &columns in [1, 2, 3, 16].iter() {
+                let points = generators::columns(&mut rng, count, columns);
+                for &position in [0, count / 2, count - 1].iter() {
+                    check_select(quick_select_points, points.clone(), position);
+                    check_select(
+                        quick_select_points_median_of_three,
+                        points.clone(),
+                        position,
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn narrow_groups_equal_x() {
+        let mut rng = StdRng::seed_from_u64(0xF1A6);
+        let mut points = generators::columns(&mut rng, 1_000, 5);
+        let pivot_x = points[17].x;
+        let lt = points.iter().filter(|p| p.x < pivot_x).count();
+        let gt = points.iter().filter(|p| p.x <= pivot_x).count();
+        assert!(gt - lt > 1);
+
+        // Selecting any position among the equal points leaves them grouped and in place
+        assert!(narrow(&mut points, 17, lt, &|p: &Point| p.x).is_none());
+
+        assert!(points[..lt].iter().all(|p| p.x < pivot_x));
+        assert!(points[lt..gt].iter().all(|p| p.x == pivot_x));
+        assert!(points[gt..].iter().all(|p| p.x > pivot_x));
+    }
+
+    #[test]
+    fn median_of_medians_pivot_is_central() {
+        let mut rng = StdRng::seed_from_u64(0xB0B);
+        for &count in [6, 25, 99, 1_000, 4_321].iter() {
+            let mut points = generators::uniform(&mut rng, count);
+            let pivot = find_pivot_median_of_medians(&mut points);
+            let pivot_x = points[pivot].x;
+
+            // At least 3 of the 5 points in half of the groups are on each side, counting the pivot
+            let bound = 3 * (count / 5 / 2);
+            assert!(points.iter().filter(|p| p.x <= pivot_x).count() >= bound);
+            assert!(points.iter().filter(|p| p.x >= pivot_x).count() >= bound);
+        }
+    }
+
+    /// Checks that `select_by_key` agrees with `select_nth_unstable_by` on the selected key, and
+    /// that the items on each side are ordered around it.
+    fn check_select_by_key<T: Clone, K: PartialOrd + std::fmt::Debug>(
+        items: &[T],
+        nth: usize,
+        key: impl Fn(&T) -> K,
+    ) {
+        let mut expected = items.to_vec();
+        let (_, expected_nth, _) =
+            expected.select_nth_unstable_by(nth, |a, b| key(a).partial_cmp(&key(b)).unwrap());
+        let expected_key = key(expected_nth);
+
+        let mut actual = items.to_vec();
+        let (left, right) = select_by_key(&mut actual, nth, &key);
+
+        assert_eq!(left.len(), nth);
+        assert_eq!(key(&right[0]), expected_key);
+        assert!(left.iter().all(|t| key(t) <= expected_key));
+        assert!(right.iter().all(|t| key(t) >= expected_key));
+    }
+
+    #[test]
+    fn select_by_key_matches_std() {
+        #[derive(Clone, Debug)]
+        struct Reading {
+            station: u32,
+            level: f64,
+        }
+
+        let mut rng = StdRng::seed_from_u64(0x5E1EC7);
+        for _ in 0..500 {
+            let count = rng.gen_range(1..300);
+            let distinct = rng.gen_range(1..=count as i32);
+            let nth = rng.gen_range(0..count);
+
+            let integers = (0..count)
+                .map(|_| rng.gen_range(0..distinct))
+                .collect::<Vec<_>>();
+            check_select_by_key(&integers, nth, |&i| i);
+            check_select_by_key(&integers, nth, |&i| -i);
+
+            let points = generators::uniform(&mut rng, count);
+            check_select_by_key(&points, nth, |p| p.y);
+
+            let readings = (0..count)
+                .map(|i| Reading {
+                    station: i as u32,
+                    level: rng.gen_range(0..distinct) as f64 / 4.0,
+                })
+                .collect::<Vec<_>>();
+            check_select_by_key(&readings, nth, |r| r.level);
+            check_select_by_key(&readings, nth, |r| (r.level, r.station));
+        }
+    }
 }
@@ -0,0 +1,651 @@
+use crate::geometry::{Point, PointPair};
+use fnv::FnvHashMap;
+use std::hash::{Hash, Hasher};
+use std::iter;
+
+/// A single mesh, indexed by its position along the `x` and `y` axis.
+///
+/// Indices are 128-bit, so that points as far out as those `make_points` generates, around `9e18`,
+/// still fall in distinct meshes when the size is far below one, as it is when two points are
+/// nearly duplicates. A position beyond the range of `i128` saturates to the mesh at the end of the
+/// range, which can then hold points that are far apart. That only makes queries slower, as a mesh
+/// never separates points closer than its size.
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
+pub struct MeshPoint {
+    pub(crate) xth: i128,
+    pub(crate) yth: i128,
+}
+
+impl Hash for MeshPoint {
+    /// Hashes only the low 64 bits of each index. Meshes far enough apart to differ only in the
+    /// high bits are rare, and still compare unequal.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.xth as u64);
+        state.write_u64(self.yth as u64);
+    }
+}
+
+impl MeshPoint {
+    /// Returns the mesh of a given size that a point belongs to.
+    pub(crate) fn of(point: Point, size: f64) -> Self {
+        MeshPoint {
+            xth: (point.x / size).floor() as i128,
+            yth: (point.y / size).floor() as i128,
+        }
+    }
+
+    /// Returns the mesh `dx` columns and `dy` rows away, or `None` if it is past either end of the
+    /// range of indices.
+    pub(crate) fn offset(self, dx: i128, dy: i128) -> Option<Self> {
+        Some(MeshPoint {
+            xth: self.xth.checked_add(dx)?,
+            yth: self.yth.checked_add(dy)?,
+        })
+    }
+}
+
+/// The column and row offsets of the neighbours of a mesh, including itself.
+const NEIGHBOUR_OFFSETS: [(i128, i128); 9] = [
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (-1, 0),
+    (0, 0),
+    (1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// Whether a point had any neighbour
+pub enum PointsInNeighbour {
+    /// Yes
+    Yes,
+    /// No (it was alone)
+    No,
+}
+
+/// An item that can be placed in a [`Mesh`] by its position.
+pub trait Located: Copy {
+    /// The position of the item.
+    fn position(&self) -> Point;
+}
+
+impl Located for Point {
+    fn position(&self) -> Point {
+        *self
+    }
+}
+
+/// A collection of meshes with a defined edge-to-edge size.
+///
+/// Internally, this is represented by a HashMap of x and y index to a vector of points. When an
+/// item is added into the collection, it is placed into the correct 'bucket' by finding out its
+/// closest index.
+///
+/// A mesh holds points by default, but can hold any [`Located`] item, such as a point together
+/// with an identifier, which queries then return in place of a point.
+///
+/// Besides the neighbourhood queries of the closest pair sieve, the mesh answers nearest
+/// neighbour, radius and rectangle queries for arbitrary points, and points can be removed. These
+/// are fastest when the size is close to the typical distance between points.
+///
+/// A mesh can be [`reset`](Mesh::reset) to a new size and refilled. This keeps the map, and the
+/// vectors of the meshes it had unless the size grows, so that they are reused rather than
+/// allocated again.
+pub struct Mesh<T = Point> {
+    size: f64,
+    mesh: FnvHashMap<MeshPoint, Vec<T>>,
+    /// Emptied vectors of meshes removed by `reset` or `remove`, ready to be reused.
+    spare: Vec<Vec<T>>,
+    /// The number of points in the mesh.
+    len: usize,
+}
+
+impl<T> Default for Mesh<T> {
+    fn default() -> Self {
+        Self {
+            size: 0.0,
+            mesh: FnvHashMap::default(),
+            spare: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+impl Mesh {
+    /// Finds the closest pair to the point given in its neighbourhood.
+    /// # Returns
+    /// Function returns `None` if it is the only point in its neighbourhood.
+    pub fn closest_pair_to_point_in_neighbour(&self, point: Point) -> Option<PointPair> {
+        let point_mp = self.get_meshpoint_of_point(point);
+
+        let neighbours = self.get_neighbours_of_mesh(point_mp);
+        closest_in_neighbourhood(neighbours.map(|p| self.points_in_mesh(p)), point)
+    }
+}
+
+impl<T: Located + PartialEq> Mesh<T> {
+    /// Create a new mesh with a specified size.
+    /// # Panics
+    /// Function will panic if the size is zero.
+    pub fn new(size: f64) -> Self {
+        debug_assert!(size > 0.0);
+        Self {
+            size,
+            ..Self::default()
+        }
+    }
+
+    /// Remove every point from the mesh and change its size, keeping its allocations.
+    ///
+    /// The same points in larger meshes fill fewer of them, so when the size grows the vectors of
+    /// the meshes are freed rather than kept as spares that would go unused.
+    /// # Panics
+    /// Function will panic if the size is zero.
+    pub fn reset(&mut self, size: f64) {
+        debug_assert!(size > 0.0);
+        if size > self.size {
+            self.mesh.clear();
+            self.spare.clear();
+        } else {
+            self.spare.extend(self.mesh.drain().map(|(_, mut v)| {
+                v.clear();
+                v
+            }));
+        }
+        self.size = size;
+        self.len = 0;
+    }
+
+    /// Reserve room in the map for at least `additional` more meshes, so that filling it does not
+    /// hold both the old and the grown map at once.
+    pub fn reserve(&mut self, additional: usize) {
+        self.mesh.reserve(additional);
+    }
+
+    /// The edge-to-edge size of each mesh.
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    /// The number of points in the mesh.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the mesh has no points.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns every point in the mesh, in no particular order.
+    pub fn points(&self) -> impl Iterator<Item = &T> {
+        self.mesh.values().flatten()
+    }
+
+    /// Returns the vector of points in a mesh, adding the mesh if it is not in the map. New meshes
+    /// start with room for one point, as most meshes of a well-sized mesh hold only one.
+    fn mesh_mut(&mut self, point_mp: MeshPoint) -> &mut Vec<T> {
+        let spare = &mut self.spare;
+        self.len += 1;
+        self.mesh
+            .entry(point_mp)
+            .or_insert_with(|| spare.pop().unwrap_or_else(|| Vec::with_capacity(1)))
+    }
+
+    /// Add a point to the mesh, but do not check if the point has any neighbours.
+    pub fn add_point_unchecked(&mut self, point: T) {
+        let point_mp = self.get_meshpoint_of_point(point.position());
+
+        self.mesh_mut(point_mp).push(point);
+    }
+
+    /// Add a point to the mesh and returns whether the point added had any neighbours. This function
+    /// involves more lookups than the unchecked variant and requires more computation.
+    pub fn add_point(&mut self, point: T) -> PointsInNeighbour {
+        let point_mp = self.get_meshpoint_of_point(point.position());
+
+        // If any of the neighbours contain a point
+        let has_neighbour = self.neighbour_is_populated(point_mp);
+
+        self.mesh_mut(point_mp).push(point);
+
+        has_neighbour
+    }
+
+    /// Remove one copy of a point from the mesh.
+    /// # Returns
+    /// Function returns `false` if the point was not in the mesh.
+    pub fn remove(&mut self, point: T) -> bool {
+        let point_mp = self.get_meshpoint_of_point(point.position());
+        let points = match self.mesh.get_mut(&point_mp) {
+            Some(points) => points,
+            None => return false,
+        };
+        let index = match points.iter().position(|&p| p == point) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        points.swap_remove(index);
+        self.len -= 1;
+        if points.is_empty() {
+            let emptied = self.mesh.remove(&point_mp).unwrap();
+            self.spare.push(emptied);
+        }
+        true
+    }
+
+    /// Finds the point in the mesh closest to `point`, which need not be in the mesh itself.
+    ///
+    /// Rings of meshes around the mesh of `point` are searched outwards until no unsearched mesh
+    /// can hold a closer point. If the rings grow larger than the number of meshes with points,
+    /// every point is searched instead.
+    /// # Returns
+    /// Function returns `None` if the mesh is empty.
+    pub fn nearest(&self, point: Point) -> Option<T> {
+        let centre = self.get_meshpoint_of_point(point);
+        let distance = |p: T| p.position().distance_to(point);
+        let closer = |best: Option<T>, p: T| match best {
+            Some(b) if distance(b) <= distance(p) => Some(b),
+            _ => Some(p),
+        };
+
+        let mut best = None;
+        for ring in 0_i128.. {
+            // Every mesh inside the next ring has been searched
+            let searched = (2 * ring as usize + 1).pow(2);
+            if searched > 2 * self.mesh.len() {
+                return self.points().copied().fold(None, closer);
+            }
+
+            best = ring_around(centre, ring)
+                .flat_map(|m| self.points_in_mesh(m))
+                .copied()
+                .fold(best, closer);
+
+            // A point in a mesh outside this ring is at least `ring` meshes away from `point`. The
+            // bound is shrunk by a few ulps, as a point on the edge of a mesh can be placed in
+            // its neighbour by rounding
+            if let Some(b) = best {
+                let reach = ring as f64 * self.size * (1.0 - 4.0 * f64::EPSILON);
+                if distance(b).0 <= reach {
+                    return best;
+                }
+            }
+        }
+        unreachable!()
+    }
+
+    /// Returns every point in the mesh within `radius` of `centre`, inclusive, in no particular
+    /// order.
+    pub fn within_radius(&self, centre: Point, radius: f64) -> Vec<T> {
+        let from = Point {
+            x: centre.x - radius,
+            y: centre.y - radius,
+        };
+        let to = Point {
+            x: centre.x + radius,
+            y: centre.y + radius,
+        };
+        self.points_in_meshes(from, to)
+            .filter(|p| p.position().distance_to(centre).0 <= radius)
+            .copied()
+            .collect()
+    }
+
+    /// Returns every point in the mesh inside the axis-aligned rectangle with corners `from` and
+    /// `to`, inclusive, in no particular order.
+    pub fn in_rectangle(&self, from: Point, to: Point) -> Vec<T> {
+        let inside = |p: &&T| {
+            let p = p.position();
+            from.x <= p.x && p.x <= to.x && from.y <= p.y && p.y <= to.y
+        };
+        self.points_in_meshes(from, to)
+            .filter(inside)
+            .copied()
+            .collect()
+    }
+
+    /// Returns the points of every mesh that overlaps the rectangle with corners `from` and `to`.
+    /// If there are more of these meshes than meshes with points, only the meshes with points are
+    /// visited.
+    fn points_in_meshes(&self, from: Point, to: Point) -> impl Iterator<Item = &T> {
+        let low = self.get_meshpoint_of_point(from);
+        let high = self.get_meshpoint_of_point(to);
+        // The number of meshes overlapped, or `None` if it does not fit in an index
+        let span = |low: i128, high: i128| Some(high.checked_sub(low)?.checked_add(1)?.max(0));
+        let overlapped = span(low.xth, high.xth)
+            .zip(span(low.yth, high.yth))
+            .and_then(|(columns, rows)| columns.checked_mul(rows));
+        let scan_all = overlapped.is_none_or(|meshes| meshes > self.mesh.len() as i128);
+
+        let all = scan_all.then(|| {
+            self.mesh
+                .iter()
+                .filter(move |(m, _)| {
+                    (low.xth..=high.xth).contains(&m.xth) && (low.yth..=high.yth).contains(&m.yth)
+                })
+                .flat_map(|(_, points)| points)
+        });
+        let ranged = (!scan_all).then(|| {
+            (low.xth..=high.xth)
+                .flat_map(move |xth| (low.yth..=high.yth).map(move |yth| MeshPoint { xth, yth }))
+                .flat_map(move |m| self.points_in_mesh(m))
+        });
+        all.into_iter()
+            .flatten()
+            .chain(ranged.into_iter().flatten())
+    }
+
+    /// Returns the points in a single mesh.
+    pub(crate) fn points_in_mesh(&self, point_mp: MeshPoint) -> &[T] {
+        self.mesh.get(&point_mp).map_or(&[], |v| v.as_slice())
+    }
+
+    /// Checks whether a neighbourhood is populated, given a single mesh.
+    fn neighbour_is_populated(&self, point_mp: MeshPoint) -> PointsInNeighbour {
+        if self
+            .get_neighbours_of_mesh(point_mp)
+            .flat_map(|p| self.mesh.get(&p))
+            .any(|hs| hs.len() > 1)
+        {
+            PointsInNeighbour::Yes
+        } else {
+            PointsInNeighbour::No
+        }
+    }
+
+    /// Returns the mesh a point belongs to.
+    pub(crate) fn get_meshpoint_of_point(&self, point: Point) -> MeshPoint {
+        MeshPoint::of(point, self.size)
+    }
+
+    /// Returns the neighbours of a mesh, including itself. Meshes past either end of the range of
+    /// indices are left out, so that no mesh is returned twice.
+    pub(crate) fn get_neighbours_of_mesh(
+        &self,
+        meshpoint: MeshPoint,
+    ) -> impl Iterator<Item = MeshPoint> {
+        let mut neighbours = [None; 9];
+        for (neighbour, &(dx, dy)) in neighbours.iter_mut().zip(NEIGHBOUR_OFFSETS.iter()) {
+            *neighbour = meshpoint.offset(dx, dy);
+        }
+        IntoIterator::into_iter(neighbours).flatten()
+    }
+}
+
+/// Returns the meshes exactly `ring` meshes away from `centre` along either axis, leaving out any
+/// past either end of the range of indices.
+fn ring_around(centre: MeshPoint, ring: i128) -> impl Iterator<Item = MeshPoint> {
+    // The bottom and top rows, then the left and right columns between them. The ring around a
+    // single mesh is that mesh.
+    let rows = (-ring..=ring).flat_map(move |d| {
+        iter::once(centre.offset(d, -ring))
+            .chain((ring > 0).then(|| centre.offset(d, ring)))
+            .flatten()
+    });
+    let columns = (1 - ring..ring).flat_map(move |d| {
+        iter::once(centre.offset(-ring, d))
+            .chain(iter::once(centre.offset(ring, d)))
+            .flatten()
+    });
+    rows.chain(columns)
+}
+
+/// Finds the closest point to `point` among the meshes of its neighbourhood.
+pub(crate) fn closest_in_neighbourhood<'a>(
+    meshes: impl Iterator<Item = &'a [Point]>,
+    point: Point,
+) -> Option<PointPair> {
+    let closest = skip_self(meshes.flatten(), point).min_by(|a, b| {
+        a.distance_to(point)
+            .0
+            .partial_cmp(&b.distance_to(point).0)
+            .unwrap()
+    });
+
+    closest.map(|p| PointPair(point, *p))
+}
+
+/// Skips the first occurrence of `point` in an iterator of points.
+///
+/// This stops a point from being paired with itself, while still allowing it to be paired with an
+/// exact duplicate.
+pub(crate) fn skip_self<'a>(
+    points: impl Iterator<Item = &'a Point>,
+    point: Point,
+) -> impl Iterator<Item = &'a Point> {
+    let mut skipped = false;
+    points.filter(move |&&p| {
+        if !skipped && p == point {
+            skipped = true;
+            false
+        } else {
+            true
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators;
+    use rand::prelude::*;
+
+    /// Each distribution with a mesh of about 30, and about 3, meshes across its points.
+    fn meshes(rng: &mut StdRng) -> Vec<(Vec<Point>, Mesh)> {
+        let mut meshes = Vec::new();
+        for &distribution in generators::Distribution::ALL.iter() {
+            let points = distribution.generate(rng, 500);
+            let (low, high) = bounds(&points);
+            let spread = (high.x - low.x).max(high.y - low.y);
+
+            for &across in [30.0, 3.0].iter() {
+                let mut mesh = Mesh::new((spread / across).max(1.0));
+                points.iter().for_each(|p| mesh.add_point_unchecked(*p));
+                meshes.push((points.clone(), mesh));
+            }
+        }
+        meshes
+    }
+
+    /// Returns the lower left and upper right corners of the bounding box of the points.
+    fn bounds(points: &[Point]) -> (Point, Point) {
+        let low = Point {
+            x: points.iter().map(|p| p.x).fold(f64::MAX, f64::min),
+            y: points.iter().map(|p| p.y).fold(f64::MAX, f64::min),
+        };
+        let high = Point {
+            x: points.iter().map(|p| p.x).fold(f64::MIN, f64::max),
+            y: points.iter().map(|p| p.y).fold(f64::MIN, f64::max),
+        };
+        (low, high)
+    }
+
+    /// A random point in the bounding box of the points.
+    fn query(rng: &mut StdRng, points: &[Point]) -> Point {
+        let (low, high) = bounds(points);
+        Point {
+            x: low.x + rng.gen::<f64>() * (high.x - low.x),
+            y: low.y + rng.gen::<f64>() * (high.y - low.y),
+        }
+    }
+
+    fn sorted(mut points: Vec<Point>) -> Vec<Point> {
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        points
+    }
+
+    #[test]
+    fn mesh_reset_matches_new() {
+        let mut rng = StdRng::seed_from_u64(0x3E5E7);
+        let first = generators::gaussian_clusters(&mut rng, 500, 3, 1e3);
+        let second = generators::gaussian_clusters(&mut rng, 500, 3, 1e3);
+
+        let mut reused = Mesh::new(10.0);
+        first.iter().for_each(|p| reused.add_point_unchecked(*p));
+        reused.reset(250.0);
+        second.iter().for_each(|p| reused.add_point_unchecked(*p));
+
+        let mut fresh = Mesh::new(250.0);
+        second.iter().for_each(|p| fresh.add_point_unchecked(*p));
+
+        for p in first.iter().chain(&second) {
+            let pair = |m: &Mesh| m.closest_pair_to_point_in_neighbour(*p).map(|c| c.1);
+            assert_eq!(pair(&reused), pair(&fresh));
+        }
+    }
+
+    #[test]
+    fn extreme_coordinates_and_sizes() {
+        // The corners of the range `make_points` generates, with neighbours a few ulps away, and
+        // nearly duplicate points at the origin, not so near that their distance underflows
+        let far = i64::MAX as f64;
+        let points = [
+            (far, far),
+            (far - 4096.0, far),
+            (-far, far),
+            (far, -far),
+            (-far, -far),
+            (-far, -far + 2048.0),
+            (0.0, 0.0),
+            (1e-150, 0.0),
+            (0.0, -1e-150),
+        ]
+        .iter()
+        .map(|&(x, y)| Point { x, y })
+        .collect::<Vec<_>>();
+        let queries = [(0.0, 0.5), (far, 0.0), (-far, far - 1e6), (1e-150, 1e-150)];
+
+        // With a size of 1e-300, the meshes of the corners are past the range of an index
+        for &size in [1e6, 1.0, 1e-3, 1e-300].iter() {
+            let mut mesh = Mesh::new(size);
+            points.iter().for_each(|p| mesh.add_point_unchecked(*p));
+
+            for p in &points {
+                let nearest = points
+                    .iter()
+                    .filter(|&q| q != p)
+                    .map(|q| q.distance_to(*p).0)
+                    .fold(f64::INFINITY, f64::min);
+                let found = mesh
+                    .closest_pair_to_point_in_neighbour(*p)
+                    .map(|c| c.distance().0);
+                // Every point closer than the size is in the neighbourhood, and the point is
+                // never paired with itself
+                if nearest < size {
+                    assert_eq!(found, Some(nearest), "{:?} with size {}", p, size);
+                } else {
+                    assert!(
+                        found.is_none_or(|d| d >= nearest),
+                        "{:?} with size {}",
+                        p,
+                        size
+                    );
+                }
+
+                let expected = points.iter().filter(|q| q.distance_to(*p).0 <= nearest);
+                assert_eq!(mesh.within_radius(*p, nearest).len(), expected.count());
+            }
+
+            for &(x, y) in queries.iter() {
+                let q = Point { x, y };
+                let expected = points
+                    .iter()
+                    .map(|p| p.distance_to(q).0)
+                    .fold(f64::INFINITY, f64::min);
+                assert_eq!(mesh.nearest(q).map(|p| p.distance_to(q).0), Some(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_matches_bruteforce() {
+        let mut rng = StdRng::seed_from_u64(0x4EA2);
+        assert_eq!(
+            Mesh::<Point>::new(1.0).nearest(Point { x: 0.0, y: 0.0 }),
+            None
+        );
+
+        for (points, mesh) in meshes(&mut rng) {
+            for i in 0..100 {
+                // Half of the queries are points in the mesh
+                let q = if i % 2 == 0 {
+                    points[i]
+                } else {
+                    query(&mut rng, &points)
+                };
+                let expected = points
+                    .iter()
+                    .map(|p| p.distance_to(q))
+                    .fold(f64::INFINITY, |a, d| a.min(d.0));
+                assert_eq!(mesh.nearest(q).map(|p| p.distance_to(q).0), Some(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn radius_and_rectangle_match_bruteforce() {
+        let mut rng = StdRng::seed_from_u64(0x2AD1);
+        for (points, mesh) in meshes(&mut rng) {
+            for _ in 0..50 {
+                let (a, b) = (query(&mut rng, &points), query(&mut rng, &points));
+
+                let radius = a.distance_to(b).0 / 4.0;
+                let expected = points
+                    .iter()
+                    .filter(|p| p.distance_to(a).0 <= radius)
+                    .copied()
+                    .collect();
+                assert_eq!(sorted(mesh.within_radius(a, radius)), sorted(expected));
+
+                let from = Point {
+                    x: a.x.min(b.x),
+                    y: a.y.min(b.y),
+                };
+                let to = Point {
+                    x: a.x.max(b.x),
+                    y: a.y.max(b.y),
+                };
+                let expected = points
+                    .iter()
+                    .filter(|p| from.x <= p.x && p.x <= to.x && from.y <= p.y && p.y <= to.y)
+                    .copied()
+                    .collect();
+                assert_eq!(sorted(mesh.in_rectangle(from, to)), sorted(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn remove_matches_bruteforce() {
+        let mut rng = StdRng::seed_from_u64(0x2E30);
+        for (mut points, mut mesh) in meshes(&mut rng) {
+            assert!(!mesh.remove(Point { x: 0.5, y: 0.5 }));
+
+            points.shuffle(&mut rng);
+            let removed = points.split_off(points.len() / 2);
+            for p in &removed {
+                assert!(mesh.remove(*p));
+            }
+            assert_eq!(mesh.len(), points.len());
+            assert_eq!(
+                sorted(mesh.points().copied().collect()),
+                sorted(points.clone())
+            );
+
+            for p in &removed {
+                let expected = points
+                    .iter()
+                    .map(|q| q.distance_to(*p))
+                    .fold(f64::INFINITY, |a, d| a.min(d.0));
+                assert_eq!(
+                    mesh.nearest(*p).map(|q| q.distance_to(*p).0),
+                    Some(expected)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use crate::closest::{Algorithm, Options};
+use crate::geometry::{Point, PointPair};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Inputs with at most this many points are also checked against the brute-force reference.
+pub const DEFAULT_REFERENCE_THRESHOLD: usize = 5_000;
+
+/// Finds the closest pair by comparing every pair of points. This is the `O(n^2)` reference that
+/// every solver is checked against.
+/// # Panics
+/// Function will panic if there are fewer than two points.
+pub fn reference_closest_pair(points: &[Point]) -> PointPair {
+    assert!(points.len() >= 2);
+
+    let mut minimum = PointPair(points[0], points[1]);
+    let mut minimum_distance = minimum.distance();
+
+    for (i, &a) in points.iter().enumerate() {
+        for &b in &points[i + 1..] {
+            let d = a.distance_to(b);
+            if d < minimum_distance {
+                minimum = PointPair(a, b);
+                minimum_distance = d;
+            }
+        }
+    }
+    minimum
+}
+
+/// A disagreement between a solver and the expected answer.
+#[derive(Clone, Debug)]
+pub enum Disagreement {
+    /// The solver panicked.
+    Panicked { solver: &'static str },
+    /// The solver returned a pair that is not two distinct points of the input.
+    InvalidPair {
+        solver: &'static str,
+        found: PointPair,
+    },
+    /// The solver returned a pair with a different distance.
+    Distance {
+        solver: &'static str,
+        expected: PointPair,
+        found: PointPair,
+    },
+    /// The solver returned a different pair with the same distance. This is a tie rather than an
+    /// error, but is still reported.
+    Pair {
+        solver: &'static str,
+        expected: PointPair,
+        found: PointPair,
+    },
+}
+
+impl Disagreement {
+    /// Returns whether the disagreement means that the solver is wrong.
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Disagreement::Pair { .. })
+    }
+}
+
+impl fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Disagreement::Panicked { solver } => write!(f, "{} panicked", solver),
+            Disagreement::InvalidPair { solver, found } => {
+                write!(
+                    f,
+                    "{} returned a pair not in the input: {:?}",
+                    solver, found
+                )
+            }
+            Disagreement::Distance {
+                solver,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} found distance {} ({:?}), expected {} ({:?})",
+                solver,
+                found.distance().0,
+                found,
+                expected.distance().0,
+                expected
+            ),
+            Disagreement::Pair {
+                solver,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} found a different pair with the same distance: {:?}, expected {:?}",
+                solver, found, expected
+            ),
+        }
+    }
+}
+
+/// The result of running every solver on one input.
+#[derive(Clone, Debug)]
+pub struct Report {
+    /// The pair every solver is compared against. This is the brute-force answer if the input
+    /// was small enough, otherwise the closest valid pair found by any solver.
+    pub expected: Option<PointPair>,
+    /// Whether `expected` came from the brute-force reference.
+    pub checked_against_reference: bool,
+    /// The answer of each solver, or `None` if it panicked.
+    pub results: Vec<(&'static str, Option<PointPair>)>,
+    /// All disagreements found, including ties.
+    pub disagreements: Vec<Disagreement>,
+}
+
+impl Report {
+    /// Returns whether every solver found the expected distance.
+    pub fn is_ok(&self) -> bool {
+        !self.disagreements.iter().any(|d| d.is_error())
+    }
+}
+
+/// A named solver, as passed to [`verify_solvers`].
+pub type Solver<'a> = (&'static str, &'a dyn Fn(Vec<Point>) -> PointPair);
+
+/// Returns whether two pairs consist of the same two points, in either order.
+fn same_points(a: PointPair, b: PointPair) -> bool {
+    (a.0 == b.0 && a.1 == b.1) || (a.0 == b.1 && a.1 == b.0)
+}
+
+/// Returns whether both points of the pair are distinct points (by index) of the input.
+fn is_pair_of(pair: PointPair, points: &[Point]) -> bool {
+    let count = |p: Point| points.iter().filter(|&&q| q == p).count();
+    if pair.0 == pair.1 {
+        count(pair.0) >= 2
+    } else {
+        count(pair.0) >= 1 && count(pair.1) >= 1
+    }
+}
+
+/// Runs `solver` on a copy of `points`, catching any panic.
+fn run(solver: &dyn Fn(Vec<Point>) -> PointPair, points: &[Point]) -> Option<PointPair> {
+    let points = points.to_vec();
+    panic::catch_unwind(AssertUnwindSafe(|| solver(points))).ok()
+}
+
+/// Runs the named solvers on the same input and compares their answers.
+///
+/// If the input has at most `reference_threshold` points, every solver is checked against the
+/// brute-force reference. Otherwise the solvers are only checked against each other.
+/// # Panics
+/// Function will panic if there are fewer than two points.
+pub fn verify_solvers(points: &[Point], solvers: &[Solver], reference_threshold: usize) -> Report {
+    assert!(points.len() >= 2);
+
+    let results = solvers
+        .iter()
+        .map(|(name, solver)| (*name, run(*solver, points)))
+        .collect::<Vec<_>>();
+
+    let mut disagreements = Vec::new();
+
+    // Reject answers that could not have come from this input
+    let mut valid = Vec::new();
+    for &(solver, result) in &results {
+        match result {
+            None => disagreements.push(Disagreement::Panicked { solver }),
+            Some(found) if !is_pair_of(found, points) => {
+                disagreements.push(Disagreement::InvalidPair { solver, found })
+            }
+            Some(found) => valid.push((solver, found)),
+        }
+    }
+
+    let checked_against_reference = points.len() <= reference_threshold;
+    let expected = if checked_against_reference {
+        Some(reference_closest_pair(points))
+    } else {
+        valid.iter().map(|&(_, p)| p).reduce(PointPair::min)
+    };
+
+    if let Some(expected) = expected {
+        for &(solver, found) in &valid {
+            if found.distance() != expected.distance() {
+                disagreements.push(Disagreement::Distance {
+                    solver,
+                    expected,
+                    found,
+                });
+            } else if !same_points(found, expected) {
+                disagreements.push(Disagreement::Pair {
+                    solver,
+                    expected,
+                    found,
+                });
+            }
+        }
+    }
+
+    Report {
+        expected,
+        checked_against_reference,
+        results,
+        disagreements,
+    }
+}
+
+/// Runs every algorithm in [`Algorithm::ALL`] on the same input with `options` and compares their
+/// answers. Set the seed of `options` so that the randomised solvers can be run again the same way.
+///
+/// See [`verify_solvers`].
+pub fn verify(points: &[Point], reference_threshold: usize, options: &Options) -> Report {
+    let solvers = Algorithm::ALL
+        .iter()
+        .map(|&a| (a.name(), move |p| a.find_closest_pair_with(p, options)))
+        .collect::<Vec<_>>();
+    let solvers = solvers
+        .iter()
+        .map(|(name, f)| -> Solver { (*name, f) })
+        .collect::<Vec<_>>();
+
+    verify_solvers(points, &solvers, reference_threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::closest::task_4::TASK_4_SEED;
+
+    #[test]
+    fn verify_all_agree() {
+        let points = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 10.0, y: 10.0 },
+            Point { x: 3.0, y: 4.0 },
+            Point { x: -7.0, y: 2.0 },
+            Point { x: 5.0, y: -1.0 },
+        ];
+
+        let options = Options {
+            seed: Some(TASK_4_SEED),
+            ..Options::default()
+        };
+        let report = verify(&points, DEFAULT_REFERENCE_THRESHOLD, &options);
+        assert!(report.is_ok(), "{:?}", report.disagreements);
+        assert!(report.checked_against_reference);
+        assert_eq!(report.results.len(), Algorithm::ALL.len());
+    }
+
+    #[test]
+    fn verify_catches_wrong_solver() {
+        let points = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 1.0, y: 0.0 },
+            Point { x: 5.0, y: 0.0 },
+        ];
+
+        let wrong = |p: Vec<Point>| PointPair(p[0], p[2]);
+        let invalid = |_| PointPair(Point { x: 0.0, y: 0.0 }, Point { x: 0.0, y: 0.0 });
+        let panics = |_| -> PointPair { panic!("expected panic") };
+
+        let report = verify_solvers(
+            &points,
+            &[
+                ("wrong", &wrong),
+                ("invalid", &invalid),
+                ("panics", &panics),
+            ],
+            DEFAULT_REFERENCE_THRESHOLD,
+        );
+
+        assert!(!report.is_ok());
+        assert_eq!(report.disagreements.len(), 3);
+    }
+}
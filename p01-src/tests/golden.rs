@@ -0,0 +1,62 @@
+use closest_pairs::check::{check_case, find_cases, Case, Runner};
+use closest_pairs::closest::task_4::TASK_4_SEED;
+use closest_pairs::closest::{Algorithm, Options};
+use std::path::{Path, PathBuf};
+
+/// Returns every test case in `tests/cases`.
+fn cases() -> Vec<Case> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cases");
+    let cases = find_cases(&dir).unwrap();
+    assert!(!cases.is_empty(), "no test cases in {}", dir.display());
+    cases
+}
+
+/// Runs every test case against `runner`, reporting all failures at once.
+fn check_all(runner: &Runner) {
+    let failures = cases()
+        .iter()
+        .map(|case| check_case(case, runner).unwrap())
+        .filter(|outcome| !outcome.passed())
+        .map(|outcome| format!("{} failed:\n{}", outcome.case.name(), outcome.diff()))
+        .collect::<Vec<_>>();
+
+    assert!(
+        failures.is_empty(),
+        "{}: {} case(s) failed\n{}",
+        runner.name(),
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn golden_cases_include_malformed_input() {
+    assert!(cases()
+        .iter()
+        .any(|case| case.name().ends_with("malformed_letters")));
+}
+
+#[test]
+fn golden_solvers() {
+    // Seeded, so that a failure of a randomised solver can be reproduced
+    let options = Options {
+        seed: Some(TASK_4_SEED),
+        ..Options::default()
+    };
+    for &algorithm in Algorithm::ALL.iter() {
+        check_all(&Runner::Solver(algorithm, options));
+    }
+}
+
+#[test]
+fn golden_binaries() {
+    let seed = vec!["--seed".to_string(), TASK_4_SEED.to_string()];
+    for (binary, args) in [
+        (env!("CARGO_BIN_EXE_cp1"), vec![]),
+        (env!("CARGO_BIN_EXE_cp3_1"), vec![]),
+        (env!("CARGO_BIN_EXE_cp3"), vec![]),
+        (env!("CARGO_BIN_EXE_cp4"), seed),
+    ] {
+        check_all(&Runner::Binary(PathBuf::from(binary), args));
+    }
+}
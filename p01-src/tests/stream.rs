@@ -0,0 +1,73 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs `cp_tools stream` with `args`, giving it `input` on stdin.
+fn stream(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cp_tools"))
+        .arg("stream")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Returns the lines printed by a run that succeeded.
+fn lines(output: Output) -> Vec<String> {
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn stream_points() {
+    assert_eq!(lines(stream(&[], "0 0\n3 4\n3 4.5\n")), ["inf", "5", "0.5"]);
+}
+
+#[test]
+fn stream_points_with_times() {
+    // The last point is more than 5 seconds after the others, which leave the window
+    assert_eq!(
+        lines(stream(
+            &["--seconds", "5"],
+            "0 0 1\n3 4 2\n 3 4.5 2.5 \n\n1 1 10\n"
+        )),
+        ["inf", "5", "0.5", "inf"]
+    );
+}
+
+#[test]
+fn stream_skips_a_leading_count() {
+    assert_eq!(
+        lines(stream(&["--window", "2"], "3\n0 0\n3 4\n3 4.5\n")),
+        ["inf", "5", "0.5"]
+    );
+    assert_eq!(
+        lines(stream(&["--every", "2"], "3\n0 0\n3 4\n3 4.5\n")),
+        ["5", "0.5"]
+    );
+}
+
+#[test]
+fn stream_malformed_lines() {
+    for input in &["0 0\n3 x\n", "0 0\n3\n", "0 0\n1 2 3 4\n", "0 0\n2\n"] {
+        let output = stream(&[], input);
+        assert_eq!(output.status.code(), Some(1), "{:?}", input);
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "inf\nIncorrectly formatted input\n",
+            "{:?}",
+            input
+        );
+    }
+}